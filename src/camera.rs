@@ -1,5 +1,5 @@
 //! Plugin for providing the game's camera logic, fitting around the play zone and control-display.
-use crate::{AssetHolder, GameState};
+use crate::{sokoban::WrapAround, AssetHolder, GameState};
 use bevy::{prelude::*, render::camera::ScalingMode, window::PrimaryWindow};
 use bevy_ecs_ldtk::prelude::*;
 
@@ -19,6 +19,10 @@ impl Plugin for CameraPlugin {
                 fit_camera_around_play_zone_padded
                     .run_if(not(in_state(GameState::AssetLoading)))
                     .run_if(on_event::<bevy::window::WindowResized>),
+            )
+            .add_systems(
+                Update,
+                draw_wrap_hints.run_if(in_state(GameState::Graveyard)),
             );
     }
 }
@@ -105,3 +109,43 @@ fn fit_camera_around_play_zone_padded(
         transform.translation.y = ((play_zone_size.y - padded_level_size.y as f32) / -2.).round();
     }
 }
+
+/// Draws faint lines and outward arrows on the level's edges when they wrap around, hinting that
+/// anything leaving one edge re-enters from the opposite one.
+fn draw_wrap_hints(
+    mut gizmos: Gizmos,
+    level_query: Query<(&LevelIid, &GlobalTransform)>,
+    project_assets: Res<Assets<LdtkProject>>,
+    asset_holder: Res<AssetHolder>,
+    wrap_around: Res<WrapAround>,
+) {
+    if wrap_around.is_none() {
+        return;
+    }
+
+    if let Ok((level_iid, transform)) = level_query.single() {
+        let ldtk_project = project_assets
+            .get(&asset_holder.ldtk)
+            .expect("LDtk project should already be loaded");
+        let level = ldtk_project
+            .get_raw_level_by_iid(level_iid.get())
+            .expect("level should exist in project");
+
+        let min = transform.translation().truncate();
+        let max = min + Vec2::new(level.px_wid as f32, level.px_hei as f32);
+        let center = (min + max) / 2.;
+        let color = Color::srgba(1., 1., 1., 0.25);
+        let arrow_length = 12.;
+
+        gizmos.rect_2d(Isometry2d::from_translation(center), max - min, color);
+
+        for (edge, outward) in [
+            (Vec2::new(max.x, center.y), Vec2::X),
+            (Vec2::new(min.x, center.y), Vec2::NEG_X),
+            (Vec2::new(center.x, max.y), Vec2::Y),
+            (Vec2::new(center.x, min.y), Vec2::NEG_Y),
+        ] {
+            gizmos.arrow_2d(edge, edge + outward * arrow_length, color);
+        }
+    }
+}
@@ -1,6 +1,17 @@
 //! Plugin responsible for spawning arrow blocks and movement tiles.
+//!
+//! Arrow blocks may carry a [Stride] via the "Distance" LDtk int field and "Jump" LDtk bool field,
+//! making the movement they contribute span several cells.
+//!
+//! Besides rows and columns, arrow blocks may also lie on an anti-diagonal axis.
+//! Movement tiles pick up the movement of the anti-diagonal they lie on as a third, optional,
+//! phase of their movement.
+//!
+//! "Rotating" arrow blocks, like "RotatingUpRow", turn their direction a quarter turn clockwise
+//! whenever they're pushed, and whenever a switch targeting them activates.
+//! Unlike other arrow blocks, they're not switched on and off.
 
-use std::{collections::HashMap, marker::PhantomData};
+use std::{collections::HashMap, f32::consts::FRAC_PI_2, marker::PhantomData};
 
 use bevy::{prelude::*, reflect::Enum};
 use bevy_asset_loader::{
@@ -13,9 +24,13 @@ use bevy_asset_loader::{
 use bevy_ecs_ldtk::{prelude::*, utils::grid_coords_to_translation};
 
 use crate::{
-    graveyard::{layer::GraveyardLayer, willo::WilloSets},
-    history::History,
-    sokoban::{Direction, SokobanBlock},
+    graveyard::{
+        layer::GraveyardLayer,
+        switch::{SwitchEvent, SwitchSets, SwitchTargets, Switchable},
+        willo::WilloSets,
+    },
+    history::{History, HistoryCommands, HistoryPlugin},
+    sokoban::{Direction, MoveEvent, SokobanBlock},
     utils::any_match_filter,
     GameState, UNIT_LENGTH,
 };
@@ -30,7 +45,10 @@ pub struct ArrowBlockPlugin;
 
 impl Plugin for ArrowBlockPlugin {
     fn build(&self, app: &mut App) {
-        app.configure_loading_state(
+        app.add_plugins(HistoryPlugin::<Rotating, _>::run_in_state(
+            GameState::Graveyard,
+        ))
+        .configure_loading_state(
             LoadingStateConfig::new(GameState::AssetLoading)
                 .load_collection::<MovementTileAssets>(),
         )
@@ -39,8 +57,12 @@ impl Plugin for ArrowBlockPlugin {
             MovementTileUpdateSet
                 .run_if(in_state(GameState::Graveyard).and(
                     any_match_filter::<(
-                        Or<(With<ArrowBlock<Row>>, With<ArrowBlock<Column>>)>,
-                        Changed<GridCoords>,
+                        Or<(
+                            With<ArrowBlock<Row>>,
+                            With<ArrowBlock<Column>>,
+                            With<ArrowBlock<Diagonal>>,
+                        )>,
+                        Or<(Changed<GridCoords>, Changed<Switchable>, Changed<Rotating>)>,
                     )>,
                 ))
                 .before(WilloSets::Input),
@@ -54,10 +76,22 @@ impl Plugin for ArrowBlockPlugin {
                         .pipe(GraveyardLayer::BackgroundEntities.spawn_bundles_on()),
                 ),
                 ApplyDeferred,
+                (spawn_stride_badges, spawn_diagonal_icons),
             )
                 .chain()
                 .in_set(MovementTileUpdateSet),
         )
+        .add_systems(
+            Update,
+            (
+                dim_switched_off_arrow_blocks.in_set(MovementTileUpdateSet),
+                // movement tiles pick up the rotation on the next frame, since switches are only
+                // updated after the movement tiles that input relies on
+                rotate_arrow_blocks.after(SwitchSets::UpdateSwitches),
+                turn_rotating_arrow_blocks.after(rotate_arrow_blocks),
+            )
+                .run_if(in_state(GameState::Graveyard)),
+        )
         .register_ldtk_entity::<ArrowBluckBundle<Row>>("UpRow")
         .register_ldtk_entity::<ArrowBluckBundle<Row>>("LeftRow")
         .register_ldtk_entity::<ArrowBluckBundle<Row>>("DownRow")
@@ -65,7 +99,23 @@ impl Plugin for ArrowBlockPlugin {
         .register_ldtk_entity::<ArrowBluckBundle<Column>>("UpColumn")
         .register_ldtk_entity::<ArrowBluckBundle<Column>>("LeftColumn")
         .register_ldtk_entity::<ArrowBluckBundle<Column>>("DownColumn")
-        .register_ldtk_entity::<ArrowBluckBundle<Column>>("RightColumn");
+        .register_ldtk_entity::<ArrowBluckBundle<Column>>("RightColumn")
+        .register_ldtk_entity::<ArrowBluckBundle<Diagonal>>("UpDiagonal")
+        .register_ldtk_entity::<ArrowBluckBundle<Diagonal>>("LeftDiagonal")
+        .register_ldtk_entity::<ArrowBluckBundle<Diagonal>>("DownDiagonal")
+        .register_ldtk_entity::<ArrowBluckBundle<Diagonal>>("RightDiagonal")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Row>>("RotatingUpRow")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Row>>("RotatingLeftRow")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Row>>("RotatingDownRow")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Row>>("RotatingRightRow")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Column>>("RotatingUpColumn")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Column>>("RotatingLeftColumn")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Column>>("RotatingDownColumn")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Column>>("RotatingRightColumn")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Diagonal>>("RotatingUpDiagonal")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Diagonal>>("RotatingLeftDiagonal")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Diagonal>>("RotatingDownDiagonal")
+        .register_ldtk_entity::<RotatingArrowBlockBundle<Diagonal>>("RotatingRightDiagonal");
     }
 }
 
@@ -75,18 +125,87 @@ struct MovementTileAssets {
     movement_tiles_layout: Handle<TextureAtlasLayout>,
     #[asset(path = "textures/movement-table-actions.png")]
     movement_tiles: Handle<Image>,
+    #[asset(texture_atlas(tile_size_x = 16, tile_size_y = 16, columns = 9, rows = 2))]
+    stride_badges_layout: Handle<TextureAtlasLayout>,
+    #[asset(path = "textures/movement-strides.png")]
+    stride_badges: Handle<Image>,
+}
+
+/// How far a single component of a movement carries the mover, and whether it pushes on the way.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Stride {
+    /// Move the given number of cells, one push at a time.
+    Push(u32),
+    /// Leap the given number of cells, landing without pushing anything.
+    Jump(u32),
+}
+
+impl Default for Stride {
+    fn default() -> Self {
+        Stride::Push(1)
+    }
+}
+
+impl Stride {
+    /// Returns the number of cells this stride covers.
+    pub fn distance(&self) -> u32 {
+        match self {
+            Stride::Push(distance) | Stride::Jump(distance) => *distance,
+        }
+    }
+
+    /// Combines the strides of two arrow blocks in the same row or column.
+    ///
+    /// The longer distance wins, and the result jumps if either stride jumps.
+    fn combine(self, other: Stride) -> Stride {
+        let distance = self.distance().max(other.distance());
+        match (self, other) {
+            (Stride::Jump(_), _) | (_, Stride::Jump(_)) => Stride::Jump(distance),
+            _ => Stride::Push(distance),
+        }
+    }
+
+    /// Returns the index of this stride's badge in [`MovementTileAssets::stride_badges`].
+    ///
+    /// Single-cell pushes are the norm and have no badge.
+    /// Otherwise, the first row holds push distances and the second row holds jump distances.
+    fn badge_index(&self) -> Option<usize> {
+        let column = self.distance().clamp(1, 9) as usize - 1;
+        match self {
+            Stride::Push(1) => None,
+            Stride::Push(_) => Some(column),
+            Stride::Jump(_) => Some(9 + column),
+        }
+    }
+}
+
+impl From<&EntityInstance> for Stride {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let distance = entity_instance
+            .get_maybe_int_field("Distance")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or(1)
+            .max(1) as u32;
+
+        match entity_instance.get_bool_field("Jump") {
+            Ok(true) => Stride::Jump(distance),
+            _ => Stride::Push(distance),
+        }
+    }
 }
 
 trait Dimension {
-    fn significant_coordinate(grid_coords: &GridCoords) -> &i32;
+    fn significant_coordinate(grid_coords: &GridCoords) -> i32;
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 struct Row;
 
 impl Dimension for Row {
-    fn significant_coordinate(grid_coords: &GridCoords) -> &i32 {
-        &grid_coords.y
+    fn significant_coordinate(grid_coords: &GridCoords) -> i32 {
+        grid_coords.y
     }
 }
 
@@ -94,8 +213,18 @@ impl Dimension for Row {
 struct Column;
 
 impl Dimension for Column {
-    fn significant_coordinate(grid_coords: &GridCoords) -> &i32 {
-        &grid_coords.x
+    fn significant_coordinate(grid_coords: &GridCoords) -> i32 {
+        grid_coords.x
+    }
+}
+
+/// Anti-diagonal axis, running from the top-left to the bottom-right.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Diagonal;
+
+impl Dimension for Diagonal {
+    fn significant_coordinate(grid_coords: &GridCoords) -> i32 {
+        grid_coords.x + grid_coords.y
     }
 }
 
@@ -105,6 +234,7 @@ where
     D: Dimension,
 {
     direction: Direction,
+    stride: Stride,
     phantom_data: PhantomData<D>,
 }
 
@@ -115,11 +245,17 @@ where
     fn fold_direction_into(
         &self,
         self_grid_coords: &GridCoords,
-        mut aggregate_directions: HashMap<i32, Direction>,
-    ) -> HashMap<i32, Direction> {
-        *aggregate_directions
-            .entry(*D::significant_coordinate(self_grid_coords))
-            .or_default() += self.direction;
+        rotating: Option<&Rotating>,
+        mut aggregate_directions: HashMap<i32, (Direction, Stride)>,
+    ) -> HashMap<i32, (Direction, Stride)> {
+        let (direction, stride) = aggregate_directions
+            .entry(D::significant_coordinate(self_grid_coords))
+            .or_default();
+
+        *direction += rotating.map_or(self.direction, |rotating| {
+            rotating.rotate_direction(self.direction)
+        });
+        *stride = stride.combine(self.stride);
 
         aggregate_directions
     }
@@ -144,6 +280,7 @@ where
 
         Self {
             direction,
+            stride: Stride::from(value),
             phantom_data: PhantomData,
         }
     }
@@ -161,6 +298,50 @@ where
     history: History<GridCoords>,
     #[with(SokobanBlock::new_dynamic)]
     sokoban_block: SokobanBlock,
+    switchable: Switchable,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+/// Component for arrow blocks that rotate a quarter turn clockwise whenever they're pushed, or
+/// whenever a switch targeting them activates.
+///
+/// Stores the number of quarter turns the block has rotated away from its LDtk direction.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+struct Rotating(u8);
+
+impl Rotating {
+    /// Rotates one more quarter turn clockwise.
+    fn turn(&mut self) {
+        self.0 = (self.0 + 1) % 4;
+    }
+
+    /// Rotates the given direction clockwise by the quarter turns of this block.
+    fn rotate_direction(&self, direction: Direction) -> Direction {
+        (0..self.0).fold(direction, |direction, _| match direction {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+            other => other,
+        })
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct RotatingArrowBlockBundle<D>
+where
+    D: Dimension + Send + Sync + Default + 'static,
+{
+    #[from_entity_instance]
+    arrow_block: ArrowBlock<D>,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    history: History<GridCoords>,
+    #[with(SokobanBlock::new_dynamic)]
+    sokoban_block: SokobanBlock,
+    rotating: Rotating,
+    rotating_history: History<Rotating>,
     #[sprite_sheet]
     sprite_sheet: Sprite,
 }
@@ -170,17 +351,39 @@ where
 pub struct MovementTile {
     row_move: Direction,
     column_move: Direction,
+    row_stride: Stride,
+    column_stride: Stride,
+    diagonal_move: Direction,
+    diagonal_stride: Stride,
 }
 
 impl MovementTile {
-    /// Construct a new [`MovementTile`].
+    /// Construct a new [`MovementTile`] whose components each move a single cell.
     pub fn new(row_move: Direction, column_move: Direction) -> Self {
         MovementTile {
             row_move,
             column_move,
+            row_stride: Stride::default(),
+            column_stride: Stride::default(),
+            diagonal_move: Direction::Zero,
+            diagonal_stride: Stride::default(),
         }
     }
 
+    /// Builder that sets the [Stride]s of this tile's row and column components.
+    pub fn with_strides(mut self, row_stride: Stride, column_stride: Stride) -> Self {
+        self.row_stride = row_stride;
+        self.column_stride = column_stride;
+        self
+    }
+
+    /// Builder that adds a "diagonal" (third) component to this tile's movement.
+    pub fn with_diagonal(mut self, diagonal_move: Direction, diagonal_stride: Stride) -> Self {
+        self.diagonal_move = diagonal_move;
+        self.diagonal_stride = diagonal_stride;
+        self
+    }
+
     /// Returns the "row" (first) component of this tile's movement.
     pub fn row_move(&self) -> &Direction {
         &self.row_move
@@ -191,6 +394,38 @@ impl MovementTile {
         &self.column_move
     }
 
+    /// Returns the [Stride] of the "row" (first) component of this tile's movement.
+    pub fn row_stride(&self) -> &Stride {
+        &self.row_stride
+    }
+
+    /// Returns the [Stride] of the "column" (last) component of this tile's movement.
+    pub fn column_stride(&self) -> &Stride {
+        &self.column_stride
+    }
+
+    /// Returns the "diagonal" (third) component of this tile's movement.
+    ///
+    /// This is [Direction::Zero] for tiles that don't lie on an anti-diagonal with arrow blocks.
+    pub fn diagonal_move(&self) -> &Direction {
+        &self.diagonal_move
+    }
+
+    /// Returns the [Stride] of the "diagonal" (third) component of this tile's movement.
+    pub fn diagonal_stride(&self) -> &Stride {
+        &self.diagonal_stride
+    }
+
+    /// Returns this movement as it appears in a horizontally mirrored level.
+    pub fn mirrored(&self) -> Self {
+        MovementTile {
+            row_move: self.row_move.mirrored(),
+            column_move: self.column_move.mirrored(),
+            diagonal_move: self.diagonal_move.mirrored(),
+            ..*self
+        }
+    }
+
     /// Returns the index of this movement's icon in [`MovementTileAssets::movement_tiles`].
     pub fn tileset_index(&self) -> usize {
         self.row_move.variant_index() * 9 + self.column_move.variant_index()
@@ -249,35 +484,554 @@ fn despawn_movement_tiles(
 
 /// Should be run with conservative run criteria
 fn all_movement_tiles_at_intersections(
-    row_blocks: Query<(&GridCoords, &ArrowBlock<Row>)>,
-    column_blocks: Query<(&GridCoords, &ArrowBlock<Column>)>,
+    row_blocks: Query<(
+        &GridCoords,
+        &ArrowBlock<Row>,
+        Option<&Rotating>,
+        Option<&Switchable>,
+    )>,
+    column_blocks: Query<(
+        &GridCoords,
+        &ArrowBlock<Column>,
+        Option<&Rotating>,
+        Option<&Switchable>,
+    )>,
+    diagonal_blocks: Query<(
+        &GridCoords,
+        &ArrowBlock<Diagonal>,
+        Option<&Rotating>,
+        Option<&Switchable>,
+    )>,
     movement_tile_assets: Res<MovementTileAssets>,
 ) -> Vec<MovementTileBundle> {
-    let aggregate_row_directions =
-        row_blocks
-            .iter()
-            .fold(default(), |aggregate, (grid_coords, arrow_block)| {
-                arrow_block.fold_direction_into(grid_coords, aggregate)
-            });
-
-    let aggregate_column_directions =
-        column_blocks
-            .iter()
-            .fold(default(), |aggregate, (grid_coords, arrow_block)| {
-                arrow_block.fold_direction_into(grid_coords, aggregate)
-            });
+    let aggregate_row_directions = row_blocks
+        .iter()
+        .filter(|(.., switchable)| switchable.is_none_or(Switchable::is_on))
+        .fold(
+            default(),
+            |aggregate, (grid_coords, arrow_block, rotating, _)| {
+                arrow_block.fold_direction_into(grid_coords, rotating, aggregate)
+            },
+        );
+
+    let aggregate_column_directions = column_blocks
+        .iter()
+        .filter(|(.., switchable)| switchable.is_none_or(Switchable::is_on))
+        .fold(
+            default(),
+            |aggregate, (grid_coords, arrow_block, rotating, _)| {
+                arrow_block.fold_direction_into(grid_coords, rotating, aggregate)
+            },
+        );
+
+    let aggregate_diagonal_directions = diagonal_blocks
+        .iter()
+        .filter(|(.., switchable)| switchable.is_none_or(Switchable::is_on))
+        .fold(
+            default(),
+            |aggregate, (grid_coords, arrow_block, rotating, _)| {
+                arrow_block.fold_direction_into(grid_coords, rotating, aggregate)
+            },
+        );
 
     aggregate_row_directions
         .iter()
         .cartesian_product(&aggregate_column_directions)
-        .map(|((&y, &row_move), (&x, &column_move))| {
-            let grid_coords = GridCoords::new(x, y);
-            let movement_tile = MovementTile {
-                row_move,
-                column_move,
-            };
-
-            MovementTileBundle::new(grid_coords, movement_tile, &movement_tile_assets)
-        })
+        .map(
+            |((&y, &(row_move, row_stride)), (&x, &(column_move, column_stride)))| {
+                let grid_coords = GridCoords::new(x, y);
+                let (diagonal_move, diagonal_stride) = aggregate_diagonal_directions
+                    .get(&Diagonal::significant_coordinate(&grid_coords))
+                    .copied()
+                    .unwrap_or_default();
+                let movement_tile = MovementTile::new(row_move, column_move)
+                    .with_strides(row_stride, column_stride)
+                    .with_diagonal(diagonal_move, diagonal_stride);
+
+                MovementTileBundle::new(grid_coords, movement_tile, &movement_tile_assets)
+            },
+        )
         .collect()
 }
+
+/// Marks movement tiles with the distance of their [Stride]s.
+///
+/// The row badge is placed in the top-left corner, and the column badge in the bottom-right.
+fn spawn_stride_badges(
+    mut commands: Commands,
+    movement_tiles: Query<(Entity, &MovementTile), Added<MovementTile>>,
+    movement_tile_assets: Res<MovementTileAssets>,
+) {
+    for (entity, movement_tile) in &movement_tiles {
+        let badges = [
+            (movement_tile.row_stride(), Vec2::new(-20., 20.)),
+            (movement_tile.column_stride(), Vec2::new(20., -20.)),
+        ];
+
+        for (stride, offset) in badges {
+            if let Some(index) = stride.badge_index() {
+                commands.entity(entity).with_child((
+                    Sprite {
+                        image: movement_tile_assets.stride_badges.clone(),
+                        texture_atlas: Some(TextureAtlas {
+                            layout: movement_tile_assets.stride_badges_layout.clone(),
+                            index,
+                        }),
+                        ..default()
+                    },
+                    Transform::from_translation(offset.extend(0.1)).with_scale(Vec3::splat(1.5)),
+                ));
+            }
+        }
+    }
+}
+
+/// Marks movement tiles that have a diagonal component with a small icon of that component.
+///
+/// The icon is the single-direction movement icon, placed in the top-right corner.
+fn spawn_diagonal_icons(
+    mut commands: Commands,
+    movement_tiles: Query<(Entity, &MovementTile), Added<MovementTile>>,
+    movement_tile_assets: Res<MovementTileAssets>,
+) {
+    for (entity, movement_tile) in &movement_tiles {
+        if *movement_tile.diagonal_move() == Direction::Zero {
+            continue;
+        }
+
+        let index =
+            MovementTile::new(Direction::Zero, *movement_tile.diagonal_move()).tileset_index();
+
+        commands.entity(entity).with_child((
+            Sprite {
+                image: movement_tile_assets.movement_tiles.clone(),
+                texture_atlas: Some(TextureAtlas {
+                    layout: movement_tile_assets.movement_tiles_layout.clone(),
+                    index,
+                }),
+                ..default()
+            },
+            Transform::from_xyz(20., 20., 0.1).with_scale(Vec3::splat(0.4)),
+        ));
+    }
+}
+
+fn dim_switched_off_arrow_blocks(
+    mut arrow_blocks: Query<
+        (&Switchable, &mut Sprite),
+        (
+            Or<(
+                With<ArrowBlock<Row>>,
+                With<ArrowBlock<Column>>,
+                With<ArrowBlock<Diagonal>>,
+            )>,
+            Changed<Switchable>,
+        ),
+    >,
+) {
+    for (switchable, mut sprite) in arrow_blocks.iter_mut() {
+        let alpha = if switchable.is_on() { 1. } else { 0.4 };
+        sprite.color = sprite.color.with_alpha(alpha);
+    }
+}
+
+/// Turns rotating arrow blocks that have been pushed, or targeted by a switch that activated.
+///
+/// Only pushes count, so blocks carried by conveyors or blown by wind keep their direction.
+/// Switches also fire when rewinding, but then the rotation is rewound along with them.
+fn rotate_arrow_blocks(
+    mut move_events: EventReader<MoveEvent<Direction>>,
+    mut switch_events: EventReader<SwitchEvent>,
+    mut history_commands: EventReader<HistoryCommands>,
+    switches: Query<&SwitchTargets>,
+    mut rotating_blocks: Query<(Entity, &EntityIid, &mut Rotating)>,
+) {
+    let rewound = history_commands
+        .read()
+        .any(|command| matches!(command, HistoryCommands::Rewind | HistoryCommands::Reset));
+
+    let pushed: Vec<Entity> = move_events
+        .read()
+        .filter(|event| event.pusher.is_some())
+        .map(|event| event.entity)
+        .collect();
+
+    let switched_targets: Vec<&SwitchTargets> = switch_events
+        .read()
+        .filter_map(|event| match event {
+            SwitchEvent::Activated { switch_entity } if !rewound => {
+                switches.get(*switch_entity).ok()
+            }
+            _ => None,
+        })
+        .collect();
+
+    for (entity, iid, mut rotating) in rotating_blocks.iter_mut() {
+        let turns = pushed.iter().filter(|pushed| **pushed == entity).count()
+            + switched_targets
+                .iter()
+                .filter(|targets| targets.contains(iid))
+                .count();
+
+        for _ in 0..turns {
+            rotating.turn();
+        }
+    }
+}
+
+fn turn_rotating_arrow_blocks(
+    mut rotating_blocks: Query<(&Rotating, &mut Transform), Changed<Rotating>>,
+) {
+    for (rotating, mut transform) in rotating_blocks.iter_mut() {
+        transform.rotation = Quat::from_rotation_z(-FRAC_PI_2 * rotating.0 as f32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graveyard::switch::{Switch, SwitchPlugin},
+        sokoban::{PushTracker, SokobanCommand, SokobanPlugin},
+    };
+    use bevy::{ecs::system::RunSystemOnce, state::app::StatesPlugin};
+    use bevy_ecs_ldtk::ldtk::{FieldInstance, ReferenceToAnEntityInstance};
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                HistoryPlugin::<GridCoords, _>::run_in_state(GameState::Graveyard),
+                HistoryPlugin::<Rotating, _>::run_in_state(GameState::Graveyard),
+                SwitchPlugin,
+            ))
+            .add_systems(
+                Update,
+                rotate_arrow_blocks
+                    .after(SwitchSets::UpdateSwitches)
+                    .run_if(in_state(GameState::Graveyard)),
+            )
+            .insert_resource(MovementTileAssets {
+                movement_tiles_layout: default(),
+                movement_tiles: default(),
+                stride_badges_layout: default(),
+                stride_badges: default(),
+            })
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    /// Spawns a rotating "UpRow" arrow block at (1, 0), and a "LeftColumn" one at (3, 5).
+    ///
+    /// Their movement tile is at (3, 0).
+    fn spawn_rotating_block(app: &mut App) -> Entity {
+        app.world_mut().spawn((
+            GridCoords::new(3, 5),
+            arrow_block::<Column>(Direction::Left, Stride::default()),
+            SokobanBlock::Static,
+        ));
+
+        app.world_mut()
+            .spawn((
+                EntityIid::new("rotating"),
+                GridCoords::new(1, 0),
+                History::<GridCoords>::default(),
+                arrow_block::<Row>(Direction::Up, Stride::default()),
+                SokobanBlock::Dynamic,
+                Rotating::default(),
+                History::<Rotating>::default(),
+            ))
+            .id()
+    }
+
+    fn spawn_block(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((
+                grid_coords,
+                History::<GridCoords>::default(),
+                SokobanBlock::Dynamic,
+            ))
+            .id()
+    }
+
+    /// Spawns a pressure plate at (1, 2) that targets the rotating block.
+    fn spawn_pressure_plate(app: &mut App) {
+        let targets = EntityInstance {
+            field_instances: vec![FieldInstance {
+                identifier: "Targets".to_string(),
+                tile: None,
+                field_instance_type: String::new(),
+                value: FieldValue::EntityRefs(vec![Some(ReferenceToAnEntityInstance {
+                    entity_iid: "rotating".to_string(),
+                    ..default()
+                })]),
+                def_uid: 0,
+                real_editor_values: Vec::new(),
+            }],
+            ..default()
+        };
+
+        app.world_mut().spawn((
+            GridCoords::new(1, 2),
+            Switch::from(&EntityInstance {
+                identifier: "PressurePlate".to_string(),
+                ..default()
+            }),
+            History::<Switch>::default(),
+            SwitchTargets::from(&targets),
+        ));
+    }
+
+    fn move_block(app: &mut App, entity: Entity, direction: Direction) {
+        app.world_mut().send_event(HistoryCommands::Record);
+        app.world_mut()
+            .send_event(SokobanCommand::Move { entity, direction });
+        app.update();
+    }
+
+    fn rewind(app: &mut App) {
+        app.world_mut().send_event(HistoryCommands::Rewind);
+        app.update();
+    }
+
+    fn movement_tile_at(app: &mut App, grid_coords: GridCoords) -> Option<MovementTile> {
+        app.world_mut()
+            .run_system_once(all_movement_tiles_at_intersections)
+            .unwrap()
+            .into_iter()
+            .find(|tile| tile.grid_coords == grid_coords)
+            .map(|tile| tile.movement_tile)
+    }
+
+    fn row_move_at_movement_tile(app: &mut App) -> Direction {
+        *movement_tile_at(app, GridCoords::new(3, 0))
+            .expect("rotating block should form a movement tile")
+            .row_move()
+    }
+
+    fn arrow_block<D: Dimension>(direction: Direction, stride: Stride) -> ArrowBlock<D> {
+        ArrowBlock {
+            direction,
+            stride,
+            phantom_data: PhantomData,
+        }
+    }
+
+    fn stride_instance(distance: Option<i32>, jump: bool) -> EntityInstance {
+        let field_instance = |identifier: &str, value| FieldInstance {
+            identifier: identifier.to_string(),
+            tile: None,
+            field_instance_type: String::new(),
+            value,
+            def_uid: 0,
+            real_editor_values: Vec::new(),
+        };
+
+        EntityInstance {
+            field_instances: vec![
+                field_instance("Distance", FieldValue::Int(distance)),
+                field_instance("Jump", FieldValue::Bool(jump)),
+            ],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn stride_comes_from_distance_and_jump_fields() {
+        assert_eq!(Stride::from(&stride_instance(None, false)), Stride::Push(1));
+        assert_eq!(
+            Stride::from(&stride_instance(Some(3), false)),
+            Stride::Push(3)
+        );
+        assert_eq!(
+            Stride::from(&stride_instance(Some(2), true)),
+            Stride::Jump(2)
+        );
+        assert_eq!(
+            Stride::from(&stride_instance(Some(0), true)),
+            Stride::Jump(1)
+        );
+        assert_eq!(Stride::from(&EntityInstance::default()), Stride::Push(1));
+    }
+
+    #[test]
+    fn combined_strides_take_the_longer_distance_and_jump_if_either_does() {
+        assert_eq!(Stride::Push(1).combine(Stride::Push(3)), Stride::Push(3));
+        assert_eq!(Stride::Push(3).combine(Stride::Jump(2)), Stride::Jump(3));
+        assert_eq!(Stride::Jump(2).combine(Stride::Push(1)), Stride::Jump(2));
+        assert_eq!(Stride::Jump(1).combine(Stride::Jump(1)), Stride::Jump(1));
+    }
+
+    #[test]
+    fn diagonal_blocks_aggregate_along_anti_diagonals() {
+        let aggregate = [
+            (GridCoords::new(0, 3), Direction::Up, Stride::default()),
+            (GridCoords::new(2, 1), Direction::Right, Stride::Jump(2)),
+            (GridCoords::new(2, 2), Direction::Down, Stride::default()),
+        ]
+        .iter()
+        .fold(
+            HashMap::new(),
+            |aggregate, (grid_coords, direction, stride)| {
+                arrow_block::<Diagonal>(*direction, *stride).fold_direction_into(
+                    grid_coords,
+                    None,
+                    aggregate,
+                )
+            },
+        );
+
+        assert_eq!(
+            aggregate,
+            HashMap::from([
+                (3, (Direction::UpRight, Stride::Jump(2))),
+                (4, (Direction::Down, Stride::default())),
+            ])
+        );
+    }
+
+    #[test]
+    fn movement_tiles_pick_up_their_anti_diagonal() {
+        let mut app = app_setup();
+        app.world_mut().spawn_batch([
+            (
+                GridCoords::new(0, 0),
+                arrow_block::<Row>(Direction::Up, Stride::default()),
+            ),
+            (
+                GridCoords::new(0, 1),
+                arrow_block::<Row>(Direction::Down, Stride::default()),
+            ),
+        ]);
+        app.world_mut().spawn((
+            GridCoords::new(3, 5),
+            arrow_block::<Column>(Direction::Left, Stride::default()),
+        ));
+        app.world_mut().spawn_batch([
+            (
+                GridCoords::new(1, 2),
+                arrow_block::<Diagonal>(Direction::Up, Stride::default()),
+            ),
+            (
+                GridCoords::new(2, 1),
+                arrow_block::<Diagonal>(Direction::Right, Stride::Jump(2)),
+            ),
+        ]);
+
+        let on_diagonal = movement_tile_at(&mut app, GridCoords::new(3, 0)).unwrap();
+        assert_eq!(on_diagonal.diagonal_move(), &Direction::UpRight);
+        assert_eq!(on_diagonal.diagonal_stride(), &Stride::Jump(2));
+
+        let off_diagonal = movement_tile_at(&mut app, GridCoords::new(3, 1)).unwrap();
+        assert_eq!(off_diagonal.diagonal_move(), &Direction::Zero);
+        assert_eq!(off_diagonal.diagonal_stride(), &Stride::default());
+    }
+
+    #[test]
+    fn pushed_rotating_block_turns_and_rewinds() {
+        let mut app = app_setup();
+        let block = spawn_rotating_block(&mut app);
+        let willo = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 0),
+                History::<GridCoords>::default(),
+                SokobanBlock::Dynamic,
+                PushTracker,
+            ))
+            .id();
+        app.update();
+        assert_eq!(row_move_at_movement_tile(&mut app), Direction::Up);
+
+        move_block(&mut app, willo, Direction::Right);
+        assert_eq!(app.world().get::<Rotating>(block), Some(&Rotating(1)));
+        assert_eq!(row_move_at_movement_tile(&mut app), Direction::Right);
+
+        rewind(&mut app);
+        assert_eq!(app.world().get::<Rotating>(block), Some(&Rotating(0)));
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(1, 0))
+        );
+        assert_eq!(row_move_at_movement_tile(&mut app), Direction::Up);
+    }
+
+    #[test]
+    fn rotating_block_moved_without_a_pusher_keeps_its_direction() {
+        let mut app = app_setup();
+        let block = spawn_rotating_block(&mut app);
+        let conveyed = spawn_block(&mut app, GridCoords::new(0, 0));
+        app.update();
+
+        // moved directly, like a conveyor or gust would
+        move_block(&mut app, block, Direction::Down);
+        assert_eq!(app.world().get::<Rotating>(block), Some(&Rotating(0)));
+
+        move_block(&mut app, block, Direction::Up);
+
+        // pushed, but by a block that doesn't track its pushes
+        move_block(&mut app, conveyed, Direction::Right);
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(2, 0))
+        );
+        assert_eq!(app.world().get::<Rotating>(block), Some(&Rotating(0)));
+        assert_eq!(row_move_at_movement_tile(&mut app), Direction::Up);
+    }
+
+    #[test]
+    fn switch_activation_turns_targeted_block_and_rewinds() {
+        let mut app = app_setup();
+        let block = spawn_rotating_block(&mut app);
+        spawn_pressure_plate(&mut app);
+        let stone = spawn_block(&mut app, GridCoords::new(0, 2));
+        app.update();
+
+        move_block(&mut app, stone, Direction::Right);
+        app.update();
+        assert_eq!(app.world().get::<Rotating>(block), Some(&Rotating(1)));
+        assert_eq!(row_move_at_movement_tile(&mut app), Direction::Right);
+
+        // stepping off deactivates the plate, which doesn't turn the block
+        move_block(&mut app, stone, Direction::Right);
+        app.update();
+        assert_eq!(app.world().get::<Rotating>(block), Some(&Rotating(1)));
+
+        rewind(&mut app);
+        rewind(&mut app);
+        app.update();
+        assert_eq!(app.world().get::<Rotating>(block), Some(&Rotating(0)));
+        assert_eq!(row_move_at_movement_tile(&mut app), Direction::Up);
+    }
+
+    #[test]
+    fn rotating_blocks_turn_clockwise_and_wrap() {
+        let mut rotating = Rotating::default();
+
+        let directions: Vec<Direction> = (0..5)
+            .map(|_| {
+                rotating.turn();
+                rotating.rotate_direction(Direction::Left)
+            })
+            .collect();
+
+        assert_eq!(
+            directions,
+            vec![
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+                Direction::Up,
+            ]
+        );
+    }
+}
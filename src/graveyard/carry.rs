@@ -0,0 +1,510 @@
+//! Plugin and components providing functionality for Willo carrying gravestones.
+//!
+//! Pressing [GraveyardAction::Carry] picks up the solid gravestone that Willo is facing, or puts
+//! down the gravestone they're carrying in front of them.
+//! While carried, a gravestone is not a sokoban block and floats above Willo.
+//! It's off the board until it's put down, so it doesn't react with volatiles or fill pits.
+//! Its grave action is still usable, using the movement tile under Willo rather than the one the
+//! gravestone was picked up from.
+use crate::{
+    graveyard::{
+        gravestone::GraveId,
+        multiplayer::{GraveInput, Player},
+        turn::TurnEnded,
+        volatile::{Sublimation, Volatile},
+        willo::{WilloAnimationState, WilloSets, WilloState},
+        GraveyardAction,
+    },
+    history::{FlushHistoryCommands, HistoryCommands, HistoryPlugin},
+    sokoban::{Direction, SokobanBlock, WrapAround},
+    utils::any_match_filter,
+    GameState, UNIT_LENGTH,
+};
+use bevy::prelude::*;
+use bevy_easings::{Ease, EaseFunction, *};
+use bevy_ecs_ldtk::{prelude::*, utils::grid_coords_to_translation};
+use leafwing_input_manager::prelude::*;
+use std::time::Duration;
+
+/// Plugin providing functionality for Willo carrying gravestones.
+pub struct CarryPlugin;
+
+impl Plugin for CarryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HistoryPlugin::<Carrying, _>::run_in_state(
+            GameState::Graveyard,
+        ))
+        .add_event::<CarryEvent>()
+        .init_resource::<CarrySettings>()
+        .add_systems(
+            Update,
+            (
+                carry_input
+                    .in_set(WilloSets::Input)
+                    .before(FlushHistoryCommands),
+                carry_gravestones
+                    .run_if(on_event::<CarryEvent>)
+                    .after(FlushHistoryCommands)
+                    .before(Sublimation),
+                update_carried_gravestones
+                    .run_if(any_match_filter::<Changed<Carrying>>)
+                    .after(carry_gravestones)
+                    .after(FlushHistoryCommands)
+                    .before(Sublimation),
+            )
+                .run_if(in_state(GameState::Graveyard)),
+        )
+        .add_systems(
+            PostUpdate,
+            carried_translation.run_if(not(in_state(GameState::AssetLoading))),
+        );
+    }
+}
+
+/// Component that stores the gravestone Willo is carrying, if any.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deref, DerefMut, Component)]
+pub struct Carrying(pub Option<Entity>);
+
+/// Component that marks gravestones that are being carried.
+///
+/// Kept in line with [Carrying], even when history is rewound.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Carried;
+
+#[derive(Debug, Resource)]
+struct CarrySettings {
+    carried_offset: Vec2,
+    put_down_millis: u64,
+}
+
+impl Default for CarrySettings {
+    fn default() -> Self {
+        CarrySettings {
+            carried_offset: Vec2::new(0., 12.),
+            put_down_millis: 100,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Event)]
+enum CarryEvent {
+    PickUp {
+        willo_entity: Entity,
+        stone_entity: Entity,
+    },
+    PutDown {
+        willo_entity: Entity,
+        stone_entity: Entity,
+        grid_coords: GridCoords,
+    },
+}
+
+/// Returns the grid coordinates of the tile Willo is facing, if they're facing one.
+fn in_front(
+    grid_coords: GridCoords,
+    animation_state: &WilloAnimationState,
+    wrap_around: &WrapAround,
+) -> Option<GridCoords> {
+    let direction = match animation_state {
+        WilloAnimationState::Idle(direction) | WilloAnimationState::Push(direction) => direction,
+        _ => return None,
+    };
+
+    if *direction == Direction::Zero {
+        return None;
+    }
+
+    Some(GridCoords::from(
+        wrap_around.wrap(IVec2::from(grid_coords) + direction),
+    ))
+}
+
+fn carry_input(
+    willo_query: Query<(
+        Entity,
+        &WilloState,
+        &GridCoords,
+        &WilloAnimationState,
+        &Carrying,
+        Option<&Player>,
+    )>,
+    gravestones: Query<(Entity, &GridCoords, &Volatile), (With<GraveId>, With<SokobanBlock>)>,
+    blocks: Query<&GridCoords, With<SokobanBlock>>,
+    gameplay_input: Res<ActionState<GraveyardAction>>,
+    grave_input: GraveInput,
+    wrap_around: Res<WrapAround>,
+    mut history_commands: EventWriter<HistoryCommands>,
+    mut carry_events: EventWriter<CarryEvent>,
+) {
+    // willos act together, so they must all be ready for the next action
+    if willo_query.is_empty()
+        || willo_query
+            .iter()
+            .any(|(_, willo, ..)| *willo != WilloState::Waiting)
+        || !gameplay_input.just_pressed(&GraveyardAction::Carry)
+    {
+        return;
+    }
+
+    let mut events = Vec::new();
+    let mut claimed_coords = Vec::new();
+
+    for (willo_entity, _, grid_coords, animation_state, carrying, player) in willo_query.iter() {
+        if !grave_input.controls(player) {
+            continue;
+        }
+
+        let Some(target) = in_front(*grid_coords, animation_state, &wrap_around) else {
+            continue;
+        };
+
+        // two willos can't pick up the same gravestone or put theirs down on the same tile
+        if claimed_coords.contains(&target) {
+            continue;
+        }
+
+        let event = match **carrying {
+            Some(stone_entity) => {
+                if blocks.iter().any(|block_coords| *block_coords == target) {
+                    continue;
+                }
+
+                CarryEvent::PutDown {
+                    willo_entity,
+                    stone_entity,
+                    grid_coords: target,
+                }
+            }
+            None => {
+                let Some((stone_entity, ..)) =
+                    gravestones.iter().find(|(_, stone_grid_coords, volatile)| {
+                        **stone_grid_coords == target && volatile.is_solid()
+                    })
+                else {
+                    continue;
+                };
+
+                CarryEvent::PickUp {
+                    willo_entity,
+                    stone_entity,
+                }
+            }
+        };
+
+        claimed_coords.push(target);
+        events.push(event);
+    }
+
+    if !events.is_empty() {
+        history_commands.write(HistoryCommands::Record);
+        carry_events.write_batch(events);
+    }
+}
+
+fn carry_gravestones(
+    mut carry_events: EventReader<CarryEvent>,
+    mut willo_query: Query<&mut Carrying>,
+    mut gravestones: Query<&mut GridCoords, With<GraveId>>,
+    mut turn_ended_events: EventWriter<TurnEnded>,
+) {
+    for event in carry_events.read() {
+        match *event {
+            CarryEvent::PickUp {
+                willo_entity,
+                stone_entity,
+            } => {
+                if let Ok(mut carrying) = willo_query.get_mut(willo_entity) {
+                    **carrying = Some(stone_entity);
+                }
+            }
+            CarryEvent::PutDown {
+                willo_entity,
+                stone_entity,
+                grid_coords,
+            } => {
+                if let Ok(mut carrying) = willo_query.get_mut(willo_entity) {
+                    **carrying = None;
+                }
+
+                if let Ok(mut stone_grid_coords) = gravestones.get_mut(stone_entity) {
+                    *stone_grid_coords = grid_coords;
+                }
+            }
+        }
+    }
+
+    turn_ended_events.write(TurnEnded);
+}
+
+/// Keeps [Carried] and the sokoban blocks of gravestones in line with [Carrying].
+///
+/// Carried gravestones aren't sokoban blocks, and become dynamic blocks again once put down.
+fn update_carried_gravestones(
+    mut commands: Commands,
+    carriers: Query<&Carrying>,
+    gravestones: Query<(Entity, &Volatile, Has<Carried>), With<GraveId>>,
+) {
+    for (stone_entity, volatile, was_carried) in gravestones.iter() {
+        let carried = carriers
+            .iter()
+            .any(|carrying| **carrying == Some(stone_entity));
+
+        let mut gravestone = commands.entity(stone_entity);
+
+        if carried && !was_carried {
+            gravestone.insert(Carried).remove::<SokobanBlock>();
+        } else if !carried && was_carried {
+            gravestone.remove::<Carried>();
+
+            if volatile.is_solid() {
+                gravestone.insert(SokobanBlock::Dynamic);
+            }
+        }
+    }
+}
+
+fn carried_translation(
+    mut commands: Commands,
+    mut put_down_stones: RemovedComponents<Carried>,
+    carriers: Query<(&Transform, &Carrying), Without<GraveId>>,
+    mut gravestones: Query<(&GridCoords, &mut Transform), With<GraveId>>,
+    settings: Res<CarrySettings>,
+) {
+    for (carrier_transform, carrying) in carriers.iter() {
+        let Some(stone_entity) = **carrying else {
+            continue;
+        };
+
+        if let Ok((_, mut transform)) = gravestones.get_mut(stone_entity) {
+            let xy = carrier_transform.translation.truncate() + settings.carried_offset;
+            transform.translation = xy.extend(transform.translation.z);
+        }
+    }
+
+    for stone_entity in put_down_stones.read() {
+        if let Ok((&grid_coords, transform)) = gravestones.get(stone_entity) {
+            let xy = grid_coords_to_translation(grid_coords, IVec2::splat(UNIT_LENGTH));
+
+            commands.entity(stone_entity).insert(transform.ease_to(
+                Transform::from_xyz(xy.x, xy.y, transform.translation.z),
+                EaseFunction::CubicOut,
+                EasingType::Once {
+                    duration: Duration::from_millis(settings.put_down_millis),
+                },
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graveyard::{
+            gravestone::GraveLayout,
+            multiplayer::{PlayMode, VersusRace},
+            volatile::{VolatileMaterial, VolatilePlugin},
+        },
+        history::History,
+        sokoban::{SokobanCommand, SokobanPlugin},
+    };
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_event::<TurnEnded>()
+            .init_resource::<ActionState<GraveyardAction>>()
+            .init_resource::<ActionState<GraveId>>()
+            .init_resource::<GraveLayout>()
+            .init_resource::<PlayMode>()
+            .init_resource::<VersusRace>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                HistoryPlugin::<GridCoords, _>::run_in_state(GameState::Graveyard),
+                VolatilePlugin,
+                CarryPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    /// Spawns Willo at (0, 0) and a gravestone in front of them, at (1, 0).
+    fn spawn_willo_and_gravestone(app: &mut App) -> (Entity, Entity) {
+        let willo = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 0),
+                History::<GridCoords>::default(),
+                SokobanBlock::Dynamic,
+                WilloState::Waiting,
+                Volatile::Solid,
+                History::<Volatile>::default(),
+                VolatileMaterial::new("Willo"),
+                Carrying::default(),
+                History::<Carrying>::default(),
+            ))
+            .id();
+
+        let gravestone = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(1, 0),
+                History::<GridCoords>::default(),
+                SokobanBlock::Dynamic,
+                GraveId('Q'),
+                Volatile::Solid,
+                History::<Volatile>::default(),
+                VolatileMaterial::new("Gravestone"),
+            ))
+            .id();
+
+        app.update();
+
+        (willo, gravestone)
+    }
+
+    fn carry(app: &mut App, event: CarryEvent) {
+        app.world_mut().send_event(HistoryCommands::Record);
+        app.world_mut().send_event(event);
+        app.update();
+    }
+
+    fn is_carried(app: &App, gravestone: Entity) -> bool {
+        app.world().get::<Carried>(gravestone).is_some()
+            && app.world().get::<SokobanBlock>(gravestone).is_none()
+    }
+
+    #[test]
+    fn willo_walks_through_the_tile_a_gravestone_was_lifted_from() {
+        let mut app = app_setup();
+        let (willo, gravestone) = spawn_willo_and_gravestone(&mut app);
+
+        carry(
+            &mut app,
+            CarryEvent::PickUp {
+                willo_entity: willo,
+                stone_entity: gravestone,
+            },
+        );
+        assert_eq!(
+            app.world().get::<Carrying>(willo),
+            Some(&Carrying(Some(gravestone)))
+        );
+        assert!(is_carried(&app, gravestone));
+
+        app.world_mut().send_event(HistoryCommands::Record);
+        app.world_mut().send_event(SokobanCommand::Move {
+            entity: willo,
+            direction: Direction::Right,
+        });
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(willo),
+            Some(&GridCoords::new(1, 0))
+        );
+        assert_eq!(app.world().get::<Volatile>(willo), Some(&Volatile::Solid));
+        assert_eq!(
+            app.world().get::<Volatile>(gravestone),
+            Some(&Volatile::Solid)
+        );
+
+        carry(
+            &mut app,
+            CarryEvent::PutDown {
+                willo_entity: willo,
+                stone_entity: gravestone,
+                grid_coords: GridCoords::new(2, 0),
+            },
+        );
+        assert_eq!(app.world().get::<Carrying>(willo), Some(&Carrying(None)));
+        assert_eq!(
+            app.world().get::<GridCoords>(gravestone),
+            Some(&GridCoords::new(2, 0))
+        );
+        assert!(!is_carried(&app, gravestone));
+        assert_eq!(
+            app.world().get::<SokobanBlock>(gravestone),
+            Some(&SokobanBlock::Dynamic)
+        );
+    }
+
+    #[test]
+    fn carrying_is_rewound() {
+        let mut app = app_setup();
+        let (willo, gravestone) = spawn_willo_and_gravestone(&mut app);
+
+        carry(
+            &mut app,
+            CarryEvent::PickUp {
+                willo_entity: willo,
+                stone_entity: gravestone,
+            },
+        );
+        carry(
+            &mut app,
+            CarryEvent::PutDown {
+                willo_entity: willo,
+                stone_entity: gravestone,
+                grid_coords: GridCoords::new(0, 1),
+            },
+        );
+
+        // rewinding the put-down puts the gravestone back in Willo's hands
+        app.world_mut().send_event(HistoryCommands::Rewind);
+        app.update();
+        assert_eq!(
+            app.world().get::<Carrying>(willo),
+            Some(&Carrying(Some(gravestone)))
+        );
+        assert!(is_carried(&app, gravestone));
+
+        // rewinding the pick-up puts it back where it was, as a block again
+        app.world_mut().send_event(HistoryCommands::Rewind);
+        app.update();
+        assert_eq!(app.world().get::<Carrying>(willo), Some(&Carrying(None)));
+        assert_eq!(
+            app.world().get::<GridCoords>(gravestone),
+            Some(&GridCoords::new(1, 0))
+        );
+        assert!(!is_carried(&app, gravestone));
+        assert_eq!(
+            app.world().get::<SokobanBlock>(gravestone),
+            Some(&SokobanBlock::Dynamic)
+        );
+    }
+
+    #[test]
+    fn willo_reaches_in_front_across_wrap_around_seam() {
+        let facing_left = WilloAnimationState::Idle(Direction::Left);
+
+        assert_eq!(
+            in_front(GridCoords::new(3, 2), &facing_left, &WrapAround(None)),
+            Some(GridCoords::new(2, 2))
+        );
+        assert_eq!(
+            in_front(
+                GridCoords::new(0, 2),
+                &facing_left,
+                &WrapAround(Some(IVec2::new(5, 5)))
+            ),
+            Some(GridCoords::new(4, 2))
+        );
+        assert_eq!(
+            in_front(
+                GridCoords::new(0, 2),
+                &WilloAnimationState::Dying,
+                &WrapAround(None)
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! Plugin providing functionality for optional collectibles, like candles and flowers.
+//!
+//! Willo picks up a collectible by entering its tile, even if they only pass through it on the way,
+//! like during a stride or before being warped by a teleporter.
+//! Collectibles don't count towards completing the level, but the ones held when the level is
+//! completed are recorded in the [SaveData], and counted on the level select card.
+//! Collectibles that were collected on an earlier playthrough are drawn faded.
+use crate::{
+    graveyard::{completion::LevelCompleted, willo::WilloState},
+    history::{FlushHistoryCommands, History, HistoryPlugin},
+    save::SaveData,
+    sokoban::{Direction, MoveEvent, SokobanSets},
+    utils::any_match_filter,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
+
+/// Plugin providing functionality for optional collectibles, like candles and flowers.
+pub struct CollectiblePlugin;
+
+impl Plugin for CollectiblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HistoryPlugin::<Collectible, _>::run_in_state(
+            GameState::Graveyard,
+        ))
+        .add_systems(
+            Update,
+            (
+                fade_collected_collectibles.run_if(any_match_filter::<Added<Collectible>>),
+                collect_collectibles
+                    .run_if(in_state(GameState::Graveyard))
+                    .run_if(on_event::<MoveEvent<Direction>>)
+                    .after(SokobanSets::MoveResolution)
+                    .after(FlushHistoryCommands),
+                show_collectibles.after(collect_collectibles),
+                save_collectibles.run_if(on_event::<LevelCompleted>),
+            ),
+        )
+        .register_ldtk_entity::<CollectibleBundle>("Candle")
+        .register_ldtk_entity::<CollectibleBundle>("Flower");
+    }
+}
+
+/// LDtk identifiers of the entities that are collectibles.
+const COLLECTIBLE_IDENTIFIERS: [&str; 2] = ["Candle", "Flower"];
+
+/// Returns how many collectibles the given level has, according to its LDtk data.
+pub fn collectible_count(level: &Level) -> usize {
+    level
+        .layer_instances
+        .iter()
+        .flatten()
+        .flat_map(|layer| &layer.entity_instances)
+        .filter(|entity| COLLECTIBLE_IDENTIFIERS.contains(&entity.identifier.as_str()))
+        .count()
+}
+
+/// Component that marks collectibles and whether they've been collected in this attempt.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub enum Collectible {
+    /// The collectible is still waiting for Willo.
+    #[default]
+    Uncollected,
+    /// Willo has picked up the collectible.
+    Collected,
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct CollectibleBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    collectible: Collectible,
+    collectible_history: History<Collectible>,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+const FADED_ALPHA: f32 = 0.4;
+
+/// Returns the iid of the level the given entity belongs to.
+fn level_ancestor<'a>(
+    entity: Entity,
+    parents: &Query<&ChildOf>,
+    levels: &'a Query<&LevelIid>,
+) -> Option<&'a LevelIid> {
+    parents
+        .iter_ancestors(entity)
+        .find_map(|ancestor| levels.get(ancestor).ok())
+}
+
+/// Fades collectibles that were collected on an earlier playthrough.
+fn fade_collected_collectibles(
+    mut collectibles: Query<(Entity, Ref<Collectible>, &EntityIid, &mut Sprite)>,
+    parents: Query<&ChildOf>,
+    levels: Query<&LevelIid>,
+    save_data: Res<SaveData>,
+) {
+    for (entity, collectible, entity_iid, mut sprite) in collectibles.iter_mut() {
+        let Some(level_iid) = level_ancestor(entity, &parents, &levels) else {
+            continue;
+        };
+
+        if collectible.is_added()
+            && save_data.has_collected(level_iid.as_str(), entity_iid.as_str())
+        {
+            sprite.color.set_alpha(FADED_ALPHA);
+        }
+    }
+}
+
+/// Collects everything Willo entered during their moves, and wherever they ended up after them.
+fn collect_collectibles(
+    mut move_events: EventReader<MoveEvent<Direction>>,
+    willo_query: Query<(&GridCoords, &WilloState)>,
+    mut collectibles: Query<(&GridCoords, &mut Collectible)>,
+) {
+    let mut entered_grid_coords = Vec::new();
+
+    for MoveEvent {
+        entity,
+        grid_coords,
+        ..
+    } in move_events.read()
+    {
+        let Ok((willo_grid_coords, willo_state)) = willo_query.get(*entity) else {
+            continue;
+        };
+
+        if *willo_state != WilloState::Dead {
+            entered_grid_coords.extend([*grid_coords, *willo_grid_coords]);
+        }
+    }
+
+    for (grid_coords, mut collectible) in collectibles.iter_mut() {
+        if entered_grid_coords.contains(grid_coords) && *collectible == Collectible::Uncollected {
+            *collectible = Collectible::Collected;
+        }
+    }
+}
+
+fn show_collectibles(
+    mut collectibles: Query<(&Collectible, &mut Visibility), Changed<Collectible>>,
+) {
+    for (collectible, mut visibility) in collectibles.iter_mut() {
+        *visibility = match collectible {
+            Collectible::Uncollected => Visibility::Inherited,
+            Collectible::Collected => Visibility::Hidden,
+        };
+    }
+}
+
+fn save_collectibles(
+    collectibles: Query<(Entity, &Collectible, &EntityIid)>,
+    parents: Query<&ChildOf>,
+    levels: Query<&LevelIid>,
+    mut save_data: ResMut<SaveData>,
+) {
+    for (entity, collectible, entity_iid) in collectibles.iter() {
+        let Some(level_iid) = level_ancestor(entity, &parents, &levels) else {
+            continue;
+        };
+
+        if *collectible == Collectible::Collected
+            && !save_data.has_collected(level_iid.as_str(), entity_iid.as_str())
+        {
+            save_data.collect(level_iid.as_str(), entity_iid.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sokoban::{SokobanBlock, SokobanCommand, SokobanPlugin};
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .init_resource::<SaveData>()
+            .add_event::<LevelCompleted>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                CollectiblePlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    fn spawn_collectible(app: &mut App, grid_coords: GridCoords, parent: Entity) -> Entity {
+        app.world_mut()
+            .spawn((
+                grid_coords,
+                Collectible::Uncollected,
+                EntityIid::new(format!("{grid_coords:?}")),
+                Sprite::default(),
+                Visibility::Inherited,
+                ChildOf(parent),
+            ))
+            .id()
+    }
+
+    #[test]
+    fn collectibles_are_collected_along_strides_but_not_jumps() {
+        let mut app = app_setup();
+        let level = app.world_mut().spawn(LevelIid::new("level")).id();
+        let passed = spawn_collectible(&mut app, GridCoords::new(1, 0), level);
+        let jumped = spawn_collectible(&mut app, GridCoords::new(3, 0), level);
+        let willo = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 0),
+                WilloState::Waiting,
+                SokobanBlock::Dynamic,
+            ))
+            .id();
+
+        for _ in 0..2 {
+            app.world_mut().send_event(SokobanCommand::Move {
+                entity: willo,
+                direction: Direction::Right,
+            });
+        }
+        app.update();
+
+        assert_eq!(
+            app.world().get::<Collectible>(passed),
+            Some(&Collectible::Collected)
+        );
+
+        app.world_mut().send_event(SokobanCommand::Jump {
+            entity: willo,
+            direction: Direction::Right,
+            distance: 2,
+        });
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(willo),
+            Some(&GridCoords::new(4, 0))
+        );
+        assert_eq!(
+            app.world().get::<Collectible>(jumped),
+            Some(&Collectible::Uncollected)
+        );
+    }
+
+    #[test]
+    fn collectibles_are_saved_to_their_own_level() {
+        let mut app = app_setup();
+        let level = app.world_mut().spawn(LevelIid::new("level")).id();
+        let other_level = app.world_mut().spawn(LevelIid::new("other level")).id();
+        let collectible = spawn_collectible(&mut app, GridCoords::new(1, 0), level);
+        spawn_collectible(&mut app, GridCoords::new(2, 0), level);
+        spawn_collectible(&mut app, GridCoords::new(3, 0), other_level);
+        app.update();
+
+        *app.world_mut().get_mut::<Collectible>(collectible).unwrap() = Collectible::Collected;
+        app.world_mut().send_event(LevelCompleted);
+        app.update();
+
+        let save_data = app.world().resource::<SaveData>();
+        assert_eq!(save_data.collected_count("level"), 1);
+        assert_eq!(save_data.collected_count("other level"), 0);
+        assert!(save_data.has_collected("level", "GridCoords { x: 1, y: 0 }"));
+    }
+
+    #[test]
+    fn collectibles_are_counted_from_level_data() {
+        let entity_instance = |identifier: &str| EntityInstance {
+            identifier: identifier.to_string(),
+            ..default()
+        };
+        let level = Level {
+            layer_instances: Some(vec![
+                LayerInstance {
+                    entity_instances: vec![
+                        entity_instance("Candle"),
+                        entity_instance("Willo"),
+                        entity_instance("Flower"),
+                    ],
+                    ..default()
+                },
+                LayerInstance {
+                    entity_instances: vec![entity_instance("Candle")],
+                    ..default()
+                },
+            ]),
+            ..default()
+        };
+
+        assert_eq!(collectible_count(&level), 3);
+        assert_eq!(collectible_count(&Level::default()), 0);
+    }
+}
@@ -0,0 +1,81 @@
+//! Named collision layers for graveyard entities.
+//!
+//! These give names to the bits of the sokoban plugin's [CollisionLayers].
+use crate::sokoban::CollisionLayers;
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// All named collision layers in the graveyard.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CollisionLayer {
+    /// Willo themself.
+    Willo,
+    /// Gravestones, and other blocks that Willo can push.
+    Gravestone,
+    /// Bush walls.
+    Bush,
+    /// Fence walls.
+    Fence,
+    /// Stone walls.
+    Wall,
+    /// Spectral barriers, meant to stop physical blocks but not spirits.
+    Spectral,
+    /// Exorcists, who walk through Willo rather than being blocked by them.
+    Exorcist,
+}
+
+impl CollisionLayer {
+    /// Returns the bit of this layer in a [CollisionLayers] bitmask.
+    pub fn bit(&self) -> u32 {
+        1 << *self as u32
+    }
+
+    /// Returns the bitmask containing all of the given layers.
+    pub fn mask<'a>(layers: impl IntoIterator<Item = &'a CollisionLayer>) -> u32 {
+        layers.into_iter().fold(0, |mask, layer| mask | layer.bit())
+    }
+}
+
+/// Serializable description of an entity's collision layers.
+///
+/// If `filters` are omitted, the entity collides with every layer.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollisionLayerSettings {
+    memberships: Vec<CollisionLayer>,
+    #[serde(default)]
+    filters: Option<Vec<CollisionLayer>>,
+}
+
+impl From<&CollisionLayerSettings> for CollisionLayers {
+    fn from(settings: &CollisionLayerSettings) -> Self {
+        CollisionLayers::new(
+            CollisionLayer::mask(&settings.memberships),
+            settings
+                .filters
+                .as_ref()
+                .map(CollisionLayer::mask)
+                .unwrap_or(u32::MAX),
+        )
+    }
+}
+
+/// Collision layers for Willo, who can cross spectral barriers.
+///
+/// Compatible with the `with` attribute for `#[derive(LdtkEntity)]`.
+pub fn willo_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    CollisionLayers::new(CollisionLayer::Willo.bit(), !CollisionLayer::Spectral.bit())
+}
+
+/// Collision layers for gravestones, which collide with everything.
+///
+/// Compatible with the `with` attribute for `#[derive(LdtkEntity)]`.
+pub fn gravestone_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    CollisionLayers::new(CollisionLayer::Gravestone.bit(), u32::MAX)
+}
+
+/// Collision layers for exorcists, which pass through Willo but collide with everything else.
+///
+/// Compatible with the `with` attribute for `#[derive(LdtkEntity)]`.
+pub fn exorcist_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    CollisionLayers::new(CollisionLayer::Exorcist.bit(), !CollisionLayer::Willo.bit())
+}
@@ -0,0 +1,435 @@
+//! Plugin providing victory logic for graveyard levels.
+//!
+//! Each level has a [CompletionCondition], chosen by the "Completion" LDtk level enum field.
+//! Conditions that need a number, like the amount of goals or turns, read it from the
+//! "CompletionCount" LDtk level int field, which must be at least 1.
+//! Levels without the field must have every goal met, like the original graveyard.
+//!
+//! In two-player modes, what happens after completion is decided by [complete_level].
+use crate::{
+    graveyard::{
+        goal::{Goal, GoalSets},
+        gravestone::GraveId,
+        multiplayer::{complete_level, LevelOutcome, PlayMode, VersusRace},
+        timed_volatile::TimedVolatile,
+        turn::TurnCount,
+        volatile::Volatile,
+        willo::WilloState,
+    },
+    level_transition::{LevelCardMessage, TransitionTo},
+    AssetHolder, GameState,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
+
+/// Plugin providing victory logic for graveyard levels.
+pub struct CompletionPlugin;
+
+impl Plugin for CompletionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelCompleted>()
+            .add_systems(
+                Update,
+                (
+                    spawn_completion_conditions.run_if(in_state(GameState::LevelTransition)),
+                    check_level_completion
+                        .run_if(in_state(GameState::Graveyard))
+                        .after(GoalSets::TrackGoals),
+                ),
+            )
+            .register_ldtk_entity::<ExitBundle>("Exit");
+    }
+}
+
+/// Component on the level entity defining what it takes to complete the level.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+pub enum CompletionCondition {
+    /// Every goal must be met.
+    #[default]
+    AllGoals,
+    /// Every willo must reach an exit tile.
+    Exit,
+    /// At least the given number of goals must be met.
+    SomeGoals(usize),
+    /// Every volatile tile must be sublimated.
+    ///
+    /// Timed volatiles that are going to resolidify don't count as sublimated yet.
+    AllSublimated,
+    /// Willo must survive for the given number of turns.
+    SurviveTurns(u32),
+}
+
+impl From<&Level> for CompletionCondition {
+    fn from(level: &Level) -> Self {
+        // a count of 0 would complete the level as soon as it starts
+        let count = || {
+            let count = level
+                .get_maybe_int_field("CompletionCount")
+                .ok()
+                .copied()
+                .flatten()
+                .expect("levels with a counted completion condition should have a count");
+
+            if count < 1 {
+                panic!("encountered bad level completion count: {count}");
+            }
+
+            count
+        };
+
+        match level
+            .get_maybe_enum_field("Completion")
+            .ok()
+            .and_then(|completion| completion.as_deref())
+        {
+            None | Some("AllGoals") => CompletionCondition::AllGoals,
+            Some("Exit") => CompletionCondition::Exit,
+            Some("SomeGoals") => CompletionCondition::SomeGoals(count() as usize),
+            Some("AllSublimated") => CompletionCondition::AllSublimated,
+            Some("SurviveTurns") => CompletionCondition::SurviveTurns(count() as u32),
+            Some(completion) => panic!("encountered bad level completion condition: {completion}"),
+        }
+    }
+}
+
+/// Event that fires when the current level's [CompletionCondition] is fulfilled.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Event)]
+pub struct LevelCompleted;
+
+/// Component that marks exit tiles, used by [CompletionCondition::Exit].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+pub struct Exit;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct ExitBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    exit: Exit,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+fn spawn_completion_conditions(
+    mut commands: Commands,
+    levels: Query<(Entity, &LevelIid), Added<LevelIid>>,
+    ldtk_assets: Res<Assets<LdtkProject>>,
+    asset_holder: Res<AssetHolder>,
+) {
+    let Some(ldtk_asset) = ldtk_assets.get(&asset_holder.ldtk) else {
+        return;
+    };
+
+    for (level_entity, level_iid) in levels.iter() {
+        let level = ldtk_asset
+            .get_raw_level_by_iid(level_iid.get())
+            .expect("spawned level should exist in project");
+
+        commands
+            .entity(level_entity)
+            .insert(CompletionCondition::from(level));
+    }
+}
+
+/// System param for checking a level's state against its [CompletionCondition].
+#[derive(SystemParam)]
+struct LevelProgress<'w, 's> {
+    goals: Query<'w, 's, &'static Goal>,
+    willo_query: Query<'w, 's, (&'static GridCoords, &'static WilloState)>,
+    exits: Query<'w, 's, &'static GridCoords, With<Exit>>,
+    volatile_tiles: Query<
+        'w,
+        's,
+        (&'static Volatile, Option<&'static TimedVolatile>),
+        (Without<GraveId>, Without<WilloState>),
+    >,
+}
+
+impl LevelProgress<'_, '_> {
+    /// Returns `true` if the given condition is fulfilled after the given number of turns.
+    fn fulfills(&self, condition: &CompletionCondition, turns: Option<u32>) -> bool {
+        let willo_alive = self
+            .willo_query
+            .iter()
+            .all(|(_, willo_state)| *willo_state != WilloState::Dead);
+
+        match condition {
+            // If the goal is not loaded for whatever reason (for example when hot-reloading
+            // levels), the goal will automatically be "met", loading the next level.
+            // Checking for goals prevents that.
+            CompletionCondition::AllGoals => {
+                !self.goals.is_empty() && self.goals.iter().all(Goal::is_met)
+            }
+            CompletionCondition::SomeGoals(count) => {
+                !self.goals.is_empty()
+                    && self.goals.iter().filter(|goal| goal.is_met()).count() >= *count
+            }
+            // With several willos, every one of them has to escape.
+            CompletionCondition::Exit => {
+                !self.willo_query.is_empty()
+                    && self
+                        .willo_query
+                        .iter()
+                        .all(|(willo_grid_coords, willo_state)| {
+                            *willo_state != WilloState::Dead
+                                && self
+                                    .exits
+                                    .iter()
+                                    .any(|exit_grid_coords| exit_grid_coords == willo_grid_coords)
+                        })
+            }
+            CompletionCondition::AllSublimated => {
+                !self.volatile_tiles.is_empty()
+                    && self
+                        .volatile_tiles
+                        .iter()
+                        .all(|(volatile, timed_volatile)| {
+                            !volatile.is_solid()
+                                && !matches!(timed_volatile, Some(TimedVolatile::Resolidifying(_)))
+                        })
+            }
+            CompletionCondition::SurviveTurns(count) => {
+                willo_alive && turns.is_some_and(|turns| turns >= *count)
+            }
+        }
+    }
+}
+
+fn check_level_completion(
+    mut commands: Commands,
+    levels: Query<(&CompletionCondition, Option<&TurnCount>)>,
+    level_progress: LevelProgress,
+    mut next_state: ResMut<NextState<GameState>>,
+    level_selection: Res<LevelSelection>,
+    ldtk_assets: Res<Assets<LdtkProject>>,
+    asset_holder: Res<AssetHolder>,
+    play_mode: Res<PlayMode>,
+    mut versus_race: ResMut<VersusRace>,
+    mut level_completed_events: EventWriter<LevelCompleted>,
+) {
+    let Ok((condition, turn_count)) = levels.single() else {
+        return;
+    };
+
+    let turns = turn_count.map(|turn_count| **turn_count);
+
+    let level_complete = level_progress.fulfills(condition, turns);
+
+    if !level_complete {
+        return;
+    }
+
+    next_state.set(GameState::LevelTransition);
+    level_completed_events.write(LevelCompleted);
+
+    let advance = match complete_level(*play_mode, &mut versus_race, turns.unwrap_or_default()) {
+        LevelOutcome::NextLevel(message) => {
+            if let Some(message) = message {
+                commands.insert_resource(LevelCardMessage(message));
+            }
+            true
+        }
+        LevelOutcome::Replay(message) => {
+            commands.insert_resource(LevelCardMessage(message));
+            commands.insert_resource(TransitionTo(level_selection.clone()));
+            false
+        }
+    };
+
+    if let Some(ldtk_asset) = ldtk_assets.get(&asset_holder.ldtk).filter(|_| advance) {
+        let selected_level = ldtk_asset
+            .find_raw_level_by_level_selection(&level_selection)
+            .expect("level should exist in project");
+        let level_index = ldtk_asset
+            .get_level_metadata_by_iid(&selected_level.iid)
+            .expect("level should exist in project")
+            .indices()
+            .level;
+
+        // Currently this doesn't have a time buffer like it used to.
+        // This will change as we make a more elaborate level transition workflow.
+        commands.insert_resource(TransitionTo(LevelSelection::index(level_index + 1)));
+    }
+
+    commands.spawn((
+        AudioPlayer::new(asset_holder.victory_sound.clone()),
+        PlaybackSettings::DESPAWN,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graveyard::goal::GoalPlugin;
+    use bevy::{ecs::system::RunSystemOnce, state::app::StatesPlugin};
+    use bevy_ecs_ldtk::ldtk::{FieldInstance, FieldValue};
+
+    fn field(identifier: &str, value: FieldValue) -> FieldInstance {
+        FieldInstance {
+            identifier: identifier.to_string(),
+            tile: None,
+            field_instance_type: String::new(),
+            value,
+            def_uid: 0,
+            real_editor_values: Vec::new(),
+        }
+    }
+
+    fn level(completion: &str, count: Option<i32>) -> Level {
+        Level {
+            field_instances: vec![
+                field("Completion", FieldValue::Enum(Some(completion.to_string()))),
+                field("CompletionCount", FieldValue::Int(count)),
+            ],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn counted_conditions_read_their_count() {
+        assert_eq!(
+            CompletionCondition::from(&level("SomeGoals", Some(2))),
+            CompletionCondition::SomeGoals(2)
+        );
+        assert_eq!(
+            CompletionCondition::from(&level("SurviveTurns", Some(3))),
+            CompletionCondition::SurviveTurns(3)
+        );
+        assert_eq!(
+            CompletionCondition::from(&level("Exit", None)),
+            CompletionCondition::Exit
+        );
+        assert_eq!(
+            CompletionCondition::from(&Level::default()),
+            CompletionCondition::AllGoals
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bad level completion count")]
+    fn zero_count_panics() {
+        let _ = CompletionCondition::from(&level("SurviveTurns", Some(0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "should have a count")]
+    fn missing_count_panics() {
+        let _ = CompletionCondition::from(&level("SomeGoals", None));
+    }
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .init_resource::<Time>()
+            .add_plugins(GoalPlugin)
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    fn fulfills(app: &mut App, condition: CompletionCondition, turns: Option<u32>) -> bool {
+        app.world_mut()
+            .run_system_once(move |level_progress: LevelProgress| {
+                level_progress.fulfills(&condition, turns)
+            })
+            .unwrap()
+    }
+
+    fn spawn_willo(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((grid_coords, WilloState::Waiting))
+            .id()
+    }
+
+    #[test]
+    fn goal_conditions_count_met_goals() {
+        let mut app = app_setup();
+        assert!(!fulfills(&mut app, CompletionCondition::AllGoals, None));
+
+        app.world_mut()
+            .spawn((GridCoords::new(0, 0), Goal::default()));
+        app.world_mut()
+            .spawn((GridCoords::new(1, 0), Goal::default()));
+        app.world_mut().spawn((GridCoords::new(0, 0), GraveId('Q')));
+        app.update();
+
+        assert!(!fulfills(&mut app, CompletionCondition::AllGoals, None));
+        assert!(fulfills(&mut app, CompletionCondition::SomeGoals(1), None));
+        assert!(!fulfills(&mut app, CompletionCondition::SomeGoals(2), None));
+
+        app.world_mut().spawn((GridCoords::new(1, 0), GraveId('W')));
+        app.update();
+
+        assert!(fulfills(&mut app, CompletionCondition::AllGoals, None));
+        assert!(fulfills(&mut app, CompletionCondition::SomeGoals(2), None));
+    }
+
+    #[test]
+    fn exit_needs_every_willo_on_an_exit() {
+        let mut app = app_setup();
+        assert!(!fulfills(&mut app, CompletionCondition::Exit, None));
+
+        app.world_mut().spawn((GridCoords::new(0, 0), Exit));
+        spawn_willo(&mut app, GridCoords::new(0, 0));
+        assert!(fulfills(&mut app, CompletionCondition::Exit, None));
+
+        let straggler = spawn_willo(&mut app, GridCoords::new(1, 0));
+        assert!(!fulfills(&mut app, CompletionCondition::Exit, None));
+
+        *app.world_mut().get_mut::<GridCoords>(straggler).unwrap() = GridCoords::new(0, 0);
+        assert!(fulfills(&mut app, CompletionCondition::Exit, None));
+    }
+
+    #[test]
+    fn all_sublimated_waits_for_resolidifying_timed_volatiles() {
+        let mut app = app_setup();
+        assert!(!fulfills(
+            &mut app,
+            CompletionCondition::AllSublimated,
+            None
+        ));
+
+        // gravestones and willos don't count
+        app.world_mut().spawn((Volatile::Solid, GraveId('Q')));
+        spawn_willo(&mut app, GridCoords::new(0, 0));
+
+        let tile = app.world_mut().spawn(Volatile::Solid).id();
+        let timed_tile = app
+            .world_mut()
+            .spawn((Volatile::Sublimated, TimedVolatile::Resolidifying(2)))
+            .id();
+        assert!(!fulfills(
+            &mut app,
+            CompletionCondition::AllSublimated,
+            None
+        ));
+
+        *app.world_mut().get_mut::<Volatile>(tile).unwrap() = Volatile::Sublimated;
+        assert!(!fulfills(
+            &mut app,
+            CompletionCondition::AllSublimated,
+            None
+        ));
+
+        *app.world_mut()
+            .get_mut::<TimedVolatile>(timed_tile)
+            .unwrap() = TimedVolatile::Expired;
+        assert!(fulfills(&mut app, CompletionCondition::AllSublimated, None));
+    }
+
+    #[test]
+    fn survive_turns_needs_living_willos_and_enough_turns() {
+        let mut app = app_setup();
+        let willo = spawn_willo(&mut app, GridCoords::new(0, 0));
+        let condition = CompletionCondition::SurviveTurns(3);
+
+        assert!(!fulfills(&mut app, condition, None));
+        assert!(!fulfills(&mut app, condition, Some(2)));
+        assert!(fulfills(&mut app, condition, Some(3)));
+
+        *app.world_mut().get_mut::<WilloState>(willo).unwrap() = WilloState::Dead;
+        assert!(!fulfills(&mut app, condition, Some(3)));
+    }
+}
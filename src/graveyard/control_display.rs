@@ -1,7 +1,9 @@
 //! Plugin providing functionality for the graveyard UI element showing the current controls.
+//!
+//! In [Mirrored] levels, grave actions are shown mirrored too, matching the movement on screen.
 use crate::{
     camera::PlayZonePortion,
-    graveyard::gravestone::GraveId,
+    graveyard::gravestone::{GraveId, GraveLayout},
     ui::{
         action::UiAction,
         icon_button::{IconButton, IconButtonBundle, IconButtonSet},
@@ -16,7 +18,7 @@ use bevy_ecs_ldtk::prelude::*;
 
 use super::{
     arrow_block::MovementTile, gravestone_movement_queries::GravestoneMovementQueries,
-    volatile::Sublimation, GraveyardAction,
+    multiplayer::Mirrored, volatile::Sublimation, GraveyardAction,
 };
 
 /// Plugin providing functionality for the graveyard UI element showing the current controls.
@@ -28,12 +30,15 @@ impl Plugin for ControlDisplayPlugin {
             .add_systems(
                 Update,
                 update_grave_action_buttons
-                    .run_if(in_state(GameState::Graveyard).and(
-                        any_match_filter::<(
-                            Changed<GridCoords>,
-                            Or<(With<MovementTile>, With<GraveId>)>,
-                        )>,
-                    ))
+                    .run_if(
+                        in_state(GameState::Graveyard).and(
+                            any_match_filter::<(
+                                Changed<GridCoords>,
+                                Or<(With<MovementTile>, With<GraveId>)>,
+                            )>
+                                .or(any_match_filter::<Added<Mirrored>>),
+                        ),
+                    )
                     .after(Sublimation)
                     .before(IconButtonSet),
             );
@@ -57,11 +62,26 @@ pub struct ControlDisplayAssets {
     graveyard_actions: Handle<Image>,
 }
 
+/// Returns the absolute position of a grave slot's button, as percentages of the whole layout.
+fn slot_rect(slot: UVec2, layout_size: UVec2) -> UiRect {
+    let cell_size = Vec2::splat(100.) / layout_size.as_vec2();
+    let top_left = slot.as_vec2() * cell_size;
+    let bottom_right = Vec2::splat(100.) - top_left - cell_size;
+
+    UiRect {
+        top: Val::Percent(top_left.y),
+        left: Val::Percent(top_left.x),
+        bottom: Val::Percent(bottom_right.y),
+        right: Val::Percent(bottom_right.x),
+    }
+}
+
 fn spawn_control_display(
     mut commands: Commands,
     play_zone_portion: Res<PlayZonePortion>,
     mut already_spawned: Local<bool>,
     assets: Res<ControlDisplayAssets>,
+    grave_layout: Res<GraveLayout>,
 ) {
     if !*already_spawned {
         let control_zone_ratio = 1. - **play_zone_portion;
@@ -86,90 +106,23 @@ fn spawn_control_display(
             .insert(ControlDisplay)
             .with_children(|control_display| {
                 // spawn grave ids
+                let layout_size = grave_layout.size();
+
                 control_display
                     .spawn(Node {
-                        aspect_ratio: Some(3. / 2.),
+                        aspect_ratio: Some(layout_size.x as f32 / layout_size.y as f32),
                         width: Val::Percent(80.),
                         ..default()
                     })
                     .with_children(|movement_table_action_container| {
-                        // spawn northwest
-                        movement_table_action_container
-                            .spawn(IconButtonBundle::new_with_absolute_position(
-                                IconButton::NoIcon,
-                                UiRect {
-                                    top: Val::Percent(0.),
-                                    left: Val::Percent(0.),
-                                    bottom: Val::Percent(50.),
-                                    right: Val::Percent(200. / 3.),
-                                },
-                            ))
-                            .insert(UiAction(GraveId::Northwest));
-
-                        // spawn north
-                        movement_table_action_container
-                            .spawn(IconButtonBundle::new_with_absolute_position(
-                                IconButton::NoIcon,
-                                UiRect {
-                                    top: Val::Percent(0.),
-                                    left: Val::Percent(100. / 3.),
-                                    bottom: Val::Percent(50.),
-                                    right: Val::Percent(100. / 3.),
-                                },
-                            ))
-                            .insert(UiAction(GraveId::North));
-
-                        // spawn northeast
-                        movement_table_action_container
-                            .spawn(IconButtonBundle::new_with_absolute_position(
-                                IconButton::NoIcon,
-                                UiRect {
-                                    top: Val::Percent(0.),
-                                    left: Val::Percent(200. / 3.),
-                                    bottom: Val::Percent(50.),
-                                    right: Val::Percent(0.),
-                                },
-                            ))
-                            .insert(UiAction(GraveId::Northeast));
-
-                        // spawn west
-                        movement_table_action_container
-                            .spawn(IconButtonBundle::new_with_absolute_position(
-                                IconButton::NoIcon,
-                                UiRect {
-                                    top: Val::Percent(50.),
-                                    left: Val::Percent(0.),
-                                    bottom: Val::Percent(0.),
-                                    right: Val::Percent(200. / 3.),
-                                },
-                            ))
-                            .insert(UiAction(GraveId::West));
-
-                        // spawn south
-                        movement_table_action_container
-                            .spawn(IconButtonBundle::new_with_absolute_position(
-                                IconButton::NoIcon,
-                                UiRect {
-                                    top: Val::Percent(50.),
-                                    left: Val::Percent(100. / 3.),
-                                    bottom: Val::Percent(0.),
-                                    right: Val::Percent(100. / 3.),
-                                },
-                            ))
-                            .insert(UiAction(GraveId::South));
-
-                        // spawn east
-                        movement_table_action_container
-                            .spawn(IconButtonBundle::new_with_absolute_position(
-                                IconButton::NoIcon,
-                                UiRect {
-                                    top: Val::Percent(50.),
-                                    left: Val::Percent(200. / 3.),
-                                    bottom: Val::Percent(0.),
-                                    right: Val::Percent(0.),
-                                },
-                            ))
-                            .insert(UiAction(GraveId::East));
+                        for (grave_id, slot) in grave_layout.slots() {
+                            movement_table_action_container
+                                .spawn(IconButtonBundle::new_with_absolute_position(
+                                    IconButton::NoIcon,
+                                    slot_rect(slot, layout_size),
+                                ))
+                                .insert(UiAction(grave_id));
+                        }
                     });
 
                 // spawn other grave actions
@@ -222,12 +175,17 @@ fn spawn_control_display(
 fn update_grave_action_buttons(
     gravestone_movement_queries: GravestoneMovementQueries,
     mut grave_action_buttons: Query<(&mut IconButton, &UiAction<GraveId>)>,
+    mirrored_layers: Query<(), With<Mirrored>>,
     assets: Res<ControlDisplayAssets>,
 ) {
     for (mut icon_button, action) in &mut grave_action_buttons {
         *icon_button = match gravestone_movement_queries.find_movement(action) {
             Some(movement_tile) => {
-                let index = movement_tile.tileset_index();
+                let index = if mirrored_layers.is_empty() {
+                    movement_tile.tileset_index()
+                } else {
+                    movement_tile.mirrored().tileset_index()
+                };
                 IconButton::AtlasImageIcon(UiAtlasImage {
                     texture_atlas: assets.movement_table_actions_layout.clone(),
                     image: assets.movement_table_actions.clone(),
@@ -253,7 +211,8 @@ mod tests {
         app.add_plugins(StatesPlugin)
             .init_state::<GameState>()
             .add_plugins(ControlDisplayPlugin)
-            .insert_resource(PlayZonePortion(0.5));
+            .insert_resource(PlayZonePortion(0.5))
+            .init_resource::<GraveLayout>();
 
         app
     }
@@ -327,7 +286,7 @@ mod tests {
                 northwest_pair: GravestoneMovementTilePair {
                     gravestone: world
                         .spawn(hash_movement_to_grid_coords(&self.northwest_grave_tile))
-                        .insert(GraveId::Northwest)
+                        .insert(GraveId('Q'))
                         .insert(Volatile::Solid)
                         .id(),
                     movement_tile: world
@@ -338,7 +297,7 @@ mod tests {
                 north_pair: GravestoneMovementTilePair {
                     gravestone: world
                         .spawn(hash_movement_to_grid_coords(&self.north_grave_tile))
-                        .insert(GraveId::North)
+                        .insert(GraveId('W'))
                         .insert(Volatile::Solid)
                         .id(),
                     movement_tile: world
@@ -349,7 +308,7 @@ mod tests {
                 northeast_pair: GravestoneMovementTilePair {
                     gravestone: world
                         .spawn(hash_movement_to_grid_coords(&self.northeast_grave_tile))
-                        .insert(GraveId::Northeast)
+                        .insert(GraveId('E'))
                         .insert(Volatile::Solid)
                         .id(),
                     movement_tile: world
@@ -360,7 +319,7 @@ mod tests {
                 west_pair: GravestoneMovementTilePair {
                     gravestone: world
                         .spawn(hash_movement_to_grid_coords(&self.west_grave_tile))
-                        .insert(GraveId::West)
+                        .insert(GraveId('A'))
                         .insert(Volatile::Solid)
                         .id(),
                     movement_tile: world
@@ -371,7 +330,7 @@ mod tests {
                 south_pair: GravestoneMovementTilePair {
                     gravestone: world
                         .spawn(hash_movement_to_grid_coords(&self.south_grave_tile))
-                        .insert(GraveId::South)
+                        .insert(GraveId('S'))
                         .insert(Volatile::Solid)
                         .id(),
                     movement_tile: world
@@ -382,7 +341,7 @@ mod tests {
                 east_pair: GravestoneMovementTilePair {
                     gravestone: world
                         .spawn(hash_movement_to_grid_coords(&self.east_grave_tile))
-                        .insert(GraveId::East)
+                        .insert(GraveId('D'))
                         .insert(Volatile::Solid)
                         .id(),
                     movement_tile: world
@@ -428,7 +387,7 @@ mod tests {
         initial_state_changes(&mut app);
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::Northwest),
+            get_icon_button_for_action(&mut app, GraveId('Q')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -437,7 +396,7 @@ mod tests {
         );
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::North),
+            get_icon_button_for_action(&mut app, GraveId('W')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -446,7 +405,7 @@ mod tests {
         );
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::Northeast),
+            get_icon_button_for_action(&mut app, GraveId('E')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -455,7 +414,7 @@ mod tests {
         );
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::West),
+            get_icon_button_for_action(&mut app, GraveId('A')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -464,7 +423,7 @@ mod tests {
         );
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::South),
+            get_icon_button_for_action(&mut app, GraveId('S')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -473,7 +432,7 @@ mod tests {
         );
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::East),
+            get_icon_button_for_action(&mut app, GraveId('D')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -509,6 +468,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn numpad_layout_spawns_a_button_per_slot() {
+        let mut app = app_setup();
+        app.insert_resource(
+            serde_json::from_str::<GraveLayout>(r#"{ "rows": ["QWE", "ASD", "ZXC"] }"#).unwrap(),
+        );
+        asset_setup(&mut app);
+        initial_state_changes(&mut app);
+
+        let mut grave_ids: Vec<char> = app
+            .world_mut()
+            .query::<&UiAction<GraveId>>()
+            .iter(app.world())
+            .map(|action| action.0 .0)
+            .collect();
+        grave_ids.sort();
+
+        assert_eq!(grave_ids, vec!['A', 'C', 'D', 'E', 'Q', 'S', 'W', 'X', 'Z']);
+        assert_eq!(
+            get_icon_button_for_action(&mut app, GraveId('X')),
+            &IconButton::NoIcon
+        );
+    }
+
     #[test]
     fn grave_id_buttons_change_according_to_movement_table() {
         let mut app = app_setup();
@@ -519,7 +502,7 @@ mod tests {
 
         // check initial values of a couple buttons
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::North),
+            get_icon_button_for_action(&mut app, GraveId('W')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -528,7 +511,7 @@ mod tests {
         );
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::West),
+            get_icon_button_for_action(&mut app, GraveId('A')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -556,7 +539,7 @@ mod tests {
         app.update();
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::North),
+            get_icon_button_for_action(&mut app, GraveId('W')),
             &IconButton::AtlasImageIcon(UiAtlasImage {
                 image: assets.movement_table_actions.clone(),
                 texture_atlas: assets.movement_table_actions_layout.clone(),
@@ -565,8 +548,45 @@ mod tests {
         );
 
         assert_eq!(
-            get_icon_button_for_action(&mut app, GraveId::West),
+            get_icon_button_for_action(&mut app, GraveId('A')),
             &IconButton::NoIcon,
         );
     }
+
+    #[test]
+    fn grave_id_buttons_match_the_on_screen_movement_of_mirrored_levels() {
+        let mut app = app_setup();
+        let assets = asset_setup(&mut app);
+        GravestoneMovementTilePairTestSpawner::new_valid().spawn(app.world_mut());
+
+        let layer_transform = Transform::from_scale(Vec3::new(-1., 1., 1.));
+        app.world_mut().spawn((Mirrored, layer_transform));
+        initial_state_changes(&mut app);
+
+        let on_screen = |direction: &Direction| {
+            let grid_move = (IVec2::ZERO + direction).as_vec2().extend(0.);
+            let screen_move = layer_transform.transform_point(grid_move).truncate();
+            Direction::try_from(&screen_move.as_ivec2()).unwrap()
+        };
+
+        // Q moves up, then left, in grid coordinates
+        let northwest_tile = MovementTile::new(Direction::Up, Direction::Left);
+        let on_screen_tile = MovementTile::new(
+            on_screen(northwest_tile.row_move()),
+            on_screen(northwest_tile.column_move()),
+        );
+        assert_eq!(
+            on_screen_tile,
+            MovementTile::new(Direction::Up, Direction::Right)
+        );
+
+        assert_eq!(
+            get_icon_button_for_action(&mut app, GraveId('Q')),
+            &IconButton::AtlasImageIcon(UiAtlasImage {
+                image: assets.movement_table_actions.clone(),
+                texture_atlas: assets.movement_table_actions_layout.clone(),
+                index: on_screen_tile.tileset_index()
+            })
+        );
+    }
 }
@@ -0,0 +1,210 @@
+//! Plugin providing functionality for conveyor tiles.
+//!
+//! At the end of every turn, every solid [SokobanBlock::Dynamic] block standing on a conveyor,
+//! Willo included, is moved one tile in the conveyor's direction.
+//! Conveyed blocks push like any other move.
+//! Blocks furthest along their conveyor move first, so a line of blocks on a belt moves together.
+use crate::{
+    graveyard::{
+        turn::TurnSets,
+        volatile::Volatile,
+        willo::{front_first_key, WilloState},
+    },
+    sokoban::{Direction, SokobanBlock, SokobanCommands},
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use std::collections::HashMap;
+
+/// Plugin providing functionality for conveyor tiles.
+pub struct ConveyorPlugin;
+
+const CONVEYOR_UP_INT_GRID_VALUE: i32 = 9;
+const CONVEYOR_DOWN_INT_GRID_VALUE: i32 = 10;
+const CONVEYOR_LEFT_INT_GRID_VALUE: i32 = 11;
+const CONVEYOR_RIGHT_INT_GRID_VALUE: i32 = 12;
+
+impl Plugin for ConveyorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, convey_blocks.in_set(TurnSets::ActorStep));
+
+        [
+            CONVEYOR_UP_INT_GRID_VALUE,
+            CONVEYOR_DOWN_INT_GRID_VALUE,
+            CONVEYOR_LEFT_INT_GRID_VALUE,
+            CONVEYOR_RIGHT_INT_GRID_VALUE,
+        ]
+        .into_iter()
+        .for_each(|value| {
+            app.register_ldtk_int_cell::<ConveyorBundle>(value);
+        });
+    }
+}
+
+/// Component defining the direction that a conveyor tile moves blocks in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Deref, Component)]
+pub struct Conveyor(pub Direction);
+
+impl From<IntGridCell> for Conveyor {
+    fn from(cell: IntGridCell) -> Conveyor {
+        Conveyor(match cell.value {
+            CONVEYOR_UP_INT_GRID_VALUE => Direction::Up,
+            CONVEYOR_DOWN_INT_GRID_VALUE => Direction::Down,
+            CONVEYOR_LEFT_INT_GRID_VALUE => Direction::Left,
+            CONVEYOR_RIGHT_INT_GRID_VALUE => Direction::Right,
+            value => panic!("encountered bad conveyor IntGrid value: {value}"),
+        })
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkIntCell)]
+struct ConveyorBundle {
+    #[from_int_grid_cell]
+    conveyor: Conveyor,
+}
+
+/// Returns the blocks standing on conveyors and the direction they're conveyed in, front-first.
+fn conveyed_blocks(
+    blocks: impl IntoIterator<Item = (Entity, GridCoords)>,
+    conveyors: &HashMap<GridCoords, Direction>,
+) -> Vec<(Entity, Direction)> {
+    let mut moves: Vec<_> = blocks
+        .into_iter()
+        .filter_map(|(entity, grid_coords)| {
+            conveyors
+                .get(&grid_coords)
+                .map(|direction| (entity, grid_coords, *direction))
+        })
+        .collect();
+
+    moves.sort_by_key(|(_, grid_coords, direction)| front_first_key(grid_coords, direction));
+
+    moves
+        .into_iter()
+        .map(|(entity, _, direction)| (entity, direction))
+        .collect()
+}
+
+fn convey_blocks(
+    conveyors: Query<(&GridCoords, &Conveyor)>,
+    blocks: Query<(
+        Entity,
+        &GridCoords,
+        &SokobanBlock,
+        Option<&Volatile>,
+        Option<&WilloState>,
+    )>,
+    mut sokoban_commands: SokobanCommands<Direction>,
+) {
+    let conveyors: HashMap<GridCoords, Direction> = conveyors
+        .iter()
+        .map(|(grid_coords, conveyor)| (*grid_coords, **conveyor))
+        .collect();
+
+    if conveyors.is_empty() {
+        return;
+    }
+
+    // sublimated volatiles and dead willos have left the board, even if they're still blocks
+    let blocks = blocks
+        .iter()
+        .filter(|(_, _, block, volatile, willo_state)| {
+            **block == SokobanBlock::Dynamic
+                && volatile.is_none_or(Volatile::is_solid)
+                && *willo_state != Some(&WilloState::Dead)
+        })
+        .map(|(entity, grid_coords, ..)| (entity, *grid_coords));
+
+    for (entity, direction) in conveyed_blocks(blocks, &conveyors) {
+        sokoban_commands.move_block(entity, direction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graveyard::turn::{TurnEnded, TurnPlugin},
+        sokoban::{SokobanCommand, SokobanPlugin},
+        GameState,
+    };
+    use bevy::state::app::StatesPlugin;
+
+    #[test]
+    fn conveyed_blocks_move_front_first() {
+        let [back, front, idle, up] = [0, 1, 2, 3].map(Entity::from_raw);
+
+        let conveyors = HashMap::from([
+            (GridCoords::new(0, 0), Direction::Right),
+            (GridCoords::new(1, 0), Direction::Right),
+            (GridCoords::new(4, 4), Direction::Up),
+        ]);
+
+        let blocks = [
+            (back, GridCoords::new(0, 0)),
+            (idle, GridCoords::new(2, 0)),
+            (front, GridCoords::new(1, 0)),
+            (up, GridCoords::new(4, 4)),
+        ];
+
+        let moves = conveyed_blocks(blocks, &conveyors);
+
+        assert_eq!(moves.len(), 3);
+        assert!(!moves.iter().any(|(entity, _)| *entity == idle));
+
+        let position = |entity| moves.iter().position(|(e, _)| *e == entity).unwrap();
+        assert!(position(front) < position(back));
+        assert_eq!(moves[position(up)], (up, Direction::Up));
+    }
+
+    #[test]
+    fn blocks_are_conveyed_once_at_the_end_of_each_turn() {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                TurnPlugin,
+                ConveyorPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        for x in 1..4 {
+            app.world_mut()
+                .spawn((GridCoords::new(x, 0), Conveyor(Direction::Right)));
+        }
+        let block = app
+            .world_mut()
+            .spawn((GridCoords::new(0, 0), SokobanBlock::Dynamic))
+            .id();
+
+        // moving onto a conveyor mid-turn doesn't convey the block yet
+        app.world_mut().send_event(SokobanCommand::Move {
+            entity: block,
+            direction: Direction::Right,
+        });
+        app.update();
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(1, 0))
+        );
+
+        app.world_mut().send_event(TurnEnded);
+        app.update();
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(2, 0))
+        );
+
+        app.update();
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(2, 0))
+        );
+    }
+}
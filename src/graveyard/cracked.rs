@@ -0,0 +1,257 @@
+//! Plugin providing functionality for cracked tiles, which can only be crossed once.
+//!
+//! Cracked tiles crumble as soon as Willo or a gravestone leaves them, unless any sokoban block has
+//! just moved onto them, like Willo following a pushed gravestone.
+//! - Cracked floor covers a [Pit], which opens up once the floor crumbles.
+//! - Cracked walls are floor at first, but crumble into a wall.
+use crate::{
+    graveyard::{gravestone::GraveId, pit::Pit, willo::WilloState},
+    history::{History, HistoryPlugin},
+    sokoban::{LeaveEvent, SokobanBlock, SokobanSets},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_tilemap::tiles::TileColor;
+
+/// Plugin providing functionality for cracked tiles, which can only be crossed once.
+pub struct CrackedPlugin;
+
+const CRACKED_FLOOR_INT_GRID_VALUE: i32 = 7;
+const CRACKED_WALL_INT_GRID_VALUE: i32 = 8;
+
+impl Plugin for CrackedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HistoryPlugin::<Cracked, _>::run_in_state(
+            GameState::Graveyard,
+        ))
+        .add_systems(
+            Update,
+            (
+                crumble_cracked_tiles
+                    .run_if(on_event::<LeaveEvent>)
+                    .in_set(SokobanSets::MoveResolution),
+                update_crumbled_walls,
+                tint_crumbled_tiles,
+            )
+                .run_if(in_state(GameState::Graveyard)),
+        )
+        .register_ldtk_int_cell::<CrackedFloorBundle>(CRACKED_FLOOR_INT_GRID_VALUE)
+        .register_ldtk_int_cell::<CrackedWallBundle>(CRACKED_WALL_INT_GRID_VALUE);
+    }
+}
+
+/// Component defining whether a cracked tile is still intact, and what it crumbles into.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+pub enum Cracked {
+    /// The tile is intact, and crumbles into the given kind of tile once left.
+    Intact(Crumble),
+    /// The tile has crumbled into the given kind of tile.
+    Crumbled(Crumble),
+    /// Placeholder for bundle defaults, never spawned.
+    #[default]
+    Unknown,
+}
+
+/// The kinds of tile that a cracked tile can crumble into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Crumble {
+    /// The tile crumbles into an open pit.
+    Pit,
+    /// The tile crumbles into a wall.
+    Wall,
+}
+
+#[derive(Clone, Bundle, LdtkIntCell)]
+struct CrackedFloorBundle {
+    cracked: Cracked,
+    cracked_history: History<Cracked>,
+    pit: Pit,
+    pit_history: History<Pit>,
+}
+
+impl Default for CrackedFloorBundle {
+    fn default() -> Self {
+        CrackedFloorBundle {
+            cracked: Cracked::Intact(Crumble::Pit),
+            cracked_history: History::default(),
+            pit: Pit::Covered,
+            pit_history: History::default(),
+        }
+    }
+}
+
+#[derive(Clone, Bundle, LdtkIntCell)]
+struct CrackedWallBundle {
+    cracked: Cracked,
+    cracked_history: History<Cracked>,
+}
+
+impl Default for CrackedWallBundle {
+    fn default() -> Self {
+        CrackedWallBundle {
+            cracked: Cracked::Intact(Crumble::Wall),
+            cracked_history: History::default(),
+        }
+    }
+}
+
+fn crumble_cracked_tiles(
+    mut leave_events: EventReader<LeaveEvent>,
+    crumblers: Query<(), Or<(With<WilloState>, With<GraveId>)>>,
+    blocks: Query<&GridCoords, With<SokobanBlock>>,
+    mut cracked_tiles: Query<(&GridCoords, &mut Cracked, Option<&mut Pit>)>,
+) {
+    for LeaveEvent {
+        entity,
+        grid_coords,
+    } in leave_events.read()
+    {
+        if !crumblers.contains(*entity) {
+            continue;
+        }
+
+        for (cracked_grid_coords, mut cracked, pit) in cracked_tiles.iter_mut() {
+            let Cracked::Intact(crumble) = *cracked else {
+                continue;
+            };
+
+            if cracked_grid_coords != grid_coords
+                || blocks
+                    .iter()
+                    .any(|block_grid_coords| block_grid_coords == grid_coords)
+            {
+                continue;
+            }
+
+            *cracked = Cracked::Crumbled(crumble);
+
+            if let Some(mut pit) = pit {
+                *pit = Pit::Open;
+            }
+        }
+    }
+}
+
+/// Keeps crumbled walls solid, and undoes it when a crumble is rewound.
+fn update_crumbled_walls(
+    mut commands: Commands,
+    cracked_tiles: Query<(Entity, &Cracked, Has<SokobanBlock>), Changed<Cracked>>,
+) {
+    for (entity, cracked, solid) in cracked_tiles.iter() {
+        let crumbled_wall = *cracked == Cracked::Crumbled(Crumble::Wall);
+
+        if crumbled_wall && !solid {
+            commands.entity(entity).insert(SokobanBlock::Static);
+        } else if !crumbled_wall && solid {
+            commands.entity(entity).remove::<SokobanBlock>();
+        }
+    }
+}
+
+/// Darkens crumbled tiles so they read as holes or rubble, and undoes it when a crumble is rewound.
+fn tint_crumbled_tiles(mut cracked_tiles: Query<(&Cracked, &mut TileColor), Changed<Cracked>>) {
+    for (cracked, mut tile_color) in cracked_tiles.iter_mut() {
+        *tile_color = match cracked {
+            Cracked::Crumbled(Crumble::Wall) => TileColor(Color::srgb(0.4, 0.4, 0.4)),
+            Cracked::Crumbled(Crumble::Pit) => TileColor(Color::BLACK),
+            _ => TileColor::default(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sokoban::{Direction, SokobanCommand, SokobanPlugin};
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                CrackedPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    fn spawn_cracked_wall(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((grid_coords, Cracked::Intact(Crumble::Wall)))
+            .id()
+    }
+
+    fn move_block(app: &mut App, entity: Entity, direction: Direction) {
+        app.world_mut()
+            .send_event(SokobanCommand::Move { entity, direction });
+    }
+
+    #[test]
+    fn cracked_wall_crumbles_into_a_wall_once_left() {
+        let mut app = app_setup();
+        let wall = spawn_cracked_wall(&mut app, GridCoords::new(0, 0));
+        let willo = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 0),
+                WilloState::Waiting,
+                SokobanBlock::Dynamic,
+            ))
+            .id();
+
+        move_block(&mut app, willo, Direction::Right);
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world().get::<Cracked>(wall),
+            Some(&Cracked::Crumbled(Crumble::Wall))
+        );
+        assert_eq!(
+            app.world().get::<SokobanBlock>(wall),
+            Some(&SokobanBlock::Static)
+        );
+    }
+
+    #[test]
+    fn cracked_tile_occupied_by_any_block_doesnt_crumble() {
+        let mut app = app_setup();
+        let wall = spawn_cracked_wall(&mut app, GridCoords::new(1, 0));
+        let willo = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(1, 0),
+                WilloState::Waiting,
+                SokobanBlock::Dynamic,
+            ))
+            .id();
+        let block = app
+            .world_mut()
+            .spawn((GridCoords::new(0, 0), SokobanBlock::Dynamic))
+            .id();
+
+        move_block(&mut app, willo, Direction::Right);
+        move_block(&mut app, block, Direction::Right);
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(1, 0))
+        );
+        assert_eq!(
+            app.world().get::<Cracked>(wall),
+            Some(&Cracked::Intact(Crumble::Wall))
+        );
+        assert!(app.world().get::<SokobanBlock>(wall).is_none());
+    }
+}
@@ -0,0 +1,265 @@
+//! Plugin providing functionality for doors that open when goals are met.
+//!
+//! Doors are linked to goals in LDtk via the "Goals" entity reference array field.
+//! A door is open while all of its linked goals are met, or while something stands in it, and
+//! closed otherwise.
+//! So a door that should close stays open until it's left.
+//!
+//! Doors don't keep any history of their own.
+//! Instead, their state is derived from the state of their goals and the blocks standing in them,
+//! which are both derived from [GridCoords], so doors stay in sync when history is rewound.
+use crate::{
+    graveyard::goal::{Goal, GoalEvent, GoalSets},
+    sokoban::SokobanBlock,
+    utils::any_match_filter,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+/// Plugin providing functionality for doors that open when goals are met.
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_doors
+                .run_if(in_state(GameState::Graveyard))
+                .run_if(
+                    on_event::<GoalEvent>
+                        .or(any_match_filter::<(With<SokobanBlock>, Changed<GridCoords>)>),
+                )
+                .after(GoalSets::TrackGoals),
+        )
+        .register_ldtk_entity::<DoorBundle>("Door");
+    }
+}
+
+/// Component that marks doors and stores the iids of the goals they are linked to.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Component)]
+pub struct Door {
+    goals: Vec<String>,
+}
+
+impl From<&EntityInstance> for Door {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let goals = entity_instance
+            .get_maybe_entity_refs_field("Goals")
+            .expect("doors should have a list of goals")
+            .iter()
+            .flatten()
+            .map(|entity_ref| entity_ref.entity_iid.clone())
+            .collect();
+
+        Door { goals }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct DoorBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[with(SokobanBlock::new_static)]
+    sokoban_block: SokobanBlock,
+    #[from_entity_instance]
+    door: Door,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+fn update_doors(
+    mut commands: Commands,
+    mut doors: Query<(
+        Entity,
+        &Door,
+        &GridCoords,
+        &mut Visibility,
+        Has<SokobanBlock>,
+    )>,
+    goals: Query<(&EntityIid, &Goal)>,
+    blocks: Query<&GridCoords, (With<SokobanBlock>, Without<Door>)>,
+) {
+    for (door_entity, door, door_grid_coords, mut visibility, closed) in doors.iter_mut() {
+        let goals_met = !door.goals.is_empty()
+            && door.goals.iter().all(|goal_iid| {
+                let goal_iid = EntityIid::new(goal_iid.clone());
+                goals
+                    .iter()
+                    .any(|(iid, goal)| *iid == goal_iid && goal.is_met())
+            });
+
+        let occupied = blocks
+            .iter()
+            .any(|block_grid_coords| block_grid_coords == door_grid_coords);
+
+        let open = goals_met || occupied;
+
+        if open && closed {
+            *visibility = Visibility::Hidden;
+            commands.entity(door_entity).remove::<SokobanBlock>();
+        } else if !open && !closed {
+            *visibility = Visibility::Inherited;
+            commands.entity(door_entity).insert(SokobanBlock::Static);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graveyard::{goal::GoalPlugin, gravestone::GraveId},
+        history::{History, HistoryCommands, HistoryPlugin},
+        sokoban::{Direction, SokobanCommand, SokobanPlugin},
+    };
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .init_resource::<Time>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                HistoryPlugin::<GridCoords, _>::run_in_state(GameState::Graveyard),
+                GoalPlugin,
+                DoorPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    /// Spawns a goal at (2, 0), a gravestone next to it, and a door at (0, 3) linked to the goal.
+    fn spawn_level(app: &mut App) -> (Entity, Entity) {
+        app.world_mut().spawn((
+            EntityIid::new("goal"),
+            GridCoords::new(2, 0),
+            Goal::default(),
+        ));
+
+        let gravestone = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(1, 0),
+                History::<GridCoords>::default(),
+                GraveId('Q'),
+                SokobanBlock::Dynamic,
+            ))
+            .id();
+
+        let door = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 3),
+                SokobanBlock::Static,
+                Door {
+                    goals: vec!["goal".to_string()],
+                },
+                Visibility::Inherited,
+            ))
+            .id();
+
+        (gravestone, door)
+    }
+
+    fn move_block(app: &mut App, entity: Entity, direction: Direction) {
+        app.world_mut().send_event(HistoryCommands::Record);
+        app.world_mut()
+            .send_event(SokobanCommand::Move { entity, direction });
+        app.update();
+        app.update();
+    }
+
+    fn is_closed(app: &App, door: Entity) -> bool {
+        app.world().get::<SokobanBlock>(door).is_some()
+    }
+
+    #[test]
+    fn door_opens_and_closes_with_its_goal() {
+        let mut app = app_setup();
+        let (gravestone, door) = spawn_level(&mut app);
+        app.update();
+        assert!(is_closed(&app, door));
+
+        move_block(&mut app, gravestone, Direction::Right);
+        assert!(!is_closed(&app, door));
+        assert_eq!(
+            app.world().get::<Visibility>(door),
+            Some(&Visibility::Hidden)
+        );
+
+        move_block(&mut app, gravestone, Direction::Right);
+        assert!(is_closed(&app, door));
+        assert_eq!(
+            app.world().get::<Visibility>(door),
+            Some(&Visibility::Inherited)
+        );
+    }
+
+    #[test]
+    fn door_stays_open_while_occupied() {
+        let mut app = app_setup();
+        let (gravestone, door) = spawn_level(&mut app);
+        let willo = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 2),
+                History::<GridCoords>::default(),
+                SokobanBlock::Dynamic,
+            ))
+            .id();
+
+        move_block(&mut app, gravestone, Direction::Right);
+        move_block(&mut app, willo, Direction::Up);
+        assert_eq!(
+            app.world().get::<GridCoords>(willo),
+            Some(&GridCoords::new(0, 3))
+        );
+
+        move_block(&mut app, gravestone, Direction::Right);
+        assert!(!is_closed(&app, door));
+
+        move_block(&mut app, willo, Direction::Up);
+        assert!(is_closed(&app, door));
+    }
+
+    #[test]
+    fn rewinding_into_an_open_door_keeps_it_open() {
+        let mut app = app_setup();
+        let (gravestone, door) = spawn_level(&mut app);
+        let willo = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 2),
+                History::<GridCoords>::default(),
+                SokobanBlock::Dynamic,
+            ))
+            .id();
+
+        move_block(&mut app, gravestone, Direction::Right);
+        move_block(&mut app, willo, Direction::Up);
+        move_block(&mut app, gravestone, Direction::Right);
+        move_block(&mut app, willo, Direction::Up);
+        assert!(is_closed(&app, door));
+
+        app.world_mut().send_event(HistoryCommands::Rewind);
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(willo),
+            Some(&GridCoords::new(0, 3))
+        );
+        assert!(!is_closed(&app, door));
+
+        move_block(&mut app, willo, Direction::Down);
+        assert!(is_closed(&app, door));
+    }
+}
@@ -1,18 +1,25 @@
 //! Plugin providing functionality for exorcism tiles, including death logic.
+//!
+//! The IntGrid values treated as volatile tiles, and their materials, are configured in
+//! `settings/volatile_tiles.json`.
 use crate::{
     graveyard::{
-        volatile::{Sublimation, Volatile},
+        volatile::{Sublimation, Volatile, VolatileMaterial},
         willo::WilloState,
     },
     history::History,
     ui::font_scale::{FontScale, FontSize},
     GameState,
 };
-use bevy::prelude::*;
+use bevy::{
+    ecs::{component::HookContext, world::DeferredWorld},
+    prelude::*,
+};
 use bevy_easings::{Ease, EaseFunction, *};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_ecs_tilemap::tiles::TileVisible;
-use std::time::Duration;
+use serde::Deserialize;
+use std::{collections::HashMap, fs::File, io::BufReader, time::Duration};
 
 /// Sets used by exorcism systems.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, SystemSet)]
@@ -26,7 +33,17 @@ pub struct ExorcismPlugin;
 
 impl Plugin for ExorcismPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ExorcismEvent>()
+        let asset_folder = app.get_added_plugins::<AssetPlugin>()[0].file_path.clone();
+
+        let volatile_tile_settings = load_volatile_tile_settings(asset_folder)
+            .expect("unable to load volatile tile settings");
+
+        volatile_tile_settings.keys().for_each(|value| {
+            app.register_ldtk_int_cell::<ExorcismTileBundle>(*value);
+        });
+
+        app.insert_resource(volatile_tile_settings)
+            .add_event::<ExorcismEvent>()
             .add_systems(
                 Update,
                 (
@@ -38,33 +55,91 @@ impl Plugin for ExorcismPlugin {
                     spawn_death_card.run_if(in_state(GameState::Graveyard)),
                 ),
             )
-            .add_systems(PreUpdate, make_exorcism_card_visible)
-            .register_ldtk_int_cell::<ExorcismTileBundle>(2);
+            .add_systems(PreUpdate, make_exorcism_card_visible);
     }
 }
 
-/// Event that fires when willo steps on an exorcism tile.
+/// Event that fires when a willo dies, like when stepping on an exorcism tile.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Event)]
-pub struct ExorcismEvent;
+pub struct ExorcismEvent {
+    /// The willo that died.
+    pub willo_entity: Entity,
+}
 
 /// Component that marks the "Exorcized" card UI element.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
 struct ExorcismCard;
 
+/// Resource mapping volatile tile IntGrid values to their material names.
+#[derive(Clone, Debug, Default, Deref, DerefMut, Deserialize, Resource)]
+struct VolatileTileSettings(HashMap<i32, String>);
+
+fn load_volatile_tile_settings(asset_folder: String) -> std::io::Result<VolatileTileSettings> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Ok(serde_json::from_reader(BufReader::new(File::open(
+            format!("{asset_folder}/../settings/volatile_tiles.json"),
+        )?))?)
+    }
+
+    // placed in a `#[cfg]` block rather than `if cfg!` so that changes to the file don't
+    // recompile non-wasm builds.
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(serde_json::from_str(include_str!(
+            "../../settings/volatile_tiles.json"
+        ))?)
+    }
+}
+
+/// Component storing the IntGrid value of a volatile tile.
+///
+/// Once added, it gives the tile its [VolatileMaterial] from the [VolatileTileSettings].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+#[component(on_add = set_volatile_tile_material)]
+struct VolatileTile(i32);
+
+impl From<IntGridCell> for VolatileTile {
+    fn from(cell: IntGridCell) -> VolatileTile {
+        VolatileTile(cell.value)
+    }
+}
+
+fn set_volatile_tile_material(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(VolatileTile(value)) = world.get::<VolatileTile>(entity).copied() else {
+        return;
+    };
+
+    let Some(material) = world
+        .get_resource::<VolatileTileSettings>()
+        .and_then(|settings| settings.get(&value).cloned())
+    else {
+        return;
+    };
+
+    if let Some(mut volatile_material) = world.get_mut::<VolatileMaterial>(entity) {
+        *volatile_material = VolatileMaterial::new(material);
+    }
+}
+
 #[derive(Clone, Default, Bundle, LdtkIntCell)]
 struct ExorcismTileBundle {
+    #[from_int_grid_cell]
+    volatile_tile: VolatileTile,
     volatile: Volatile,
     volatile_history: History<Volatile>,
+    material: VolatileMaterial,
+    material_history: History<VolatileMaterial>,
 }
 
 fn check_death(
-    mut willo_query: Query<(&mut WilloState, &Volatile), Changed<Volatile>>,
+    mut willo_query: Query<(Entity, &mut WilloState, &Volatile), Changed<Volatile>>,
     mut death_event_writer: EventWriter<ExorcismEvent>,
 ) {
-    if let Ok((mut willo, volatile)) = willo_query.single_mut() {
+    for (willo_entity, mut willo, volatile) in willo_query.iter_mut() {
         if !volatile.is_solid() && *willo != WilloState::Dead {
             *willo = WilloState::Dead;
-            death_event_writer.write(ExorcismEvent);
+            death_event_writer.write(ExorcismEvent { willo_entity });
         }
     }
 }
@@ -80,22 +155,41 @@ fn visually_sublimate_volatile_tiles(
 fn spawn_death_card(
     mut commands: Commands,
     assets: Res<AssetServer>,
-    willo_query: Query<&WilloState, Changed<WilloState>>,
+    willo_query: Query<&WilloState>,
+    changed_willos: Query<(), Changed<WilloState>>,
     death_cards: Query<Entity, With<ExorcismCard>>,
-    mut last_state: Local<WilloState>,
+    mut any_was_dead: Local<bool>,
 ) {
-    for state in willo_query.iter() {
-        if *state == WilloState::Dead && *last_state != WilloState::Dead {
-            // Player just died
-            commands
-                .spawn((
-                    Node::default(),
-                    BackgroundColor(Color::srgba(0., 0., 0., 0.9)),
-                    // The color renders before the transform is updated, so it needs to be
-                    // invisible for the first update
-                    Visibility::Hidden,
-                ))
-                .insert(
+    if changed_willos.is_empty() {
+        return;
+    }
+
+    // the card stays up as long as any willo is dead
+    let any_dead = willo_query.iter().any(|state| *state == WilloState::Dead);
+
+    if any_dead && !*any_was_dead {
+        // Player just died
+        commands
+            .spawn((
+                Node::default(),
+                BackgroundColor(Color::srgba(0., 0., 0., 0.9)),
+                // The color renders before the transform is updated, so it needs to be
+                // invisible for the first update
+                Visibility::Hidden,
+            ))
+            .insert(
+                Node {
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    top: Val::Percent(100.),
+                    left: Val::Percent(0.),
+                    ..Default::default()
+                }
+                .ease_to(
                     Node {
                         justify_content: JustifyContent::Center,
                         align_items: AlignItems::Center,
@@ -103,49 +197,34 @@ fn spawn_death_card(
                         flex_direction: FlexDirection::Column,
                         width: Val::Percent(100.),
                         height: Val::Percent(100.),
-                        top: Val::Percent(100.),
+                        top: Val::Percent(0.),
                         left: Val::Percent(0.),
                         ..Default::default()
-                    }
-                    .ease_to(
-                        Node {
-                            justify_content: JustifyContent::Center,
-                            align_items: AlignItems::Center,
-                            position_type: PositionType::Absolute,
-                            flex_direction: FlexDirection::Column,
-                            width: Val::Percent(100.),
-                            height: Val::Percent(100.),
-                            top: Val::Percent(0.),
-                            left: Val::Percent(0.),
-                            ..Default::default()
-                        },
-                        EaseFunction::QuadraticOut,
-                        EasingType::Once {
-                            duration: Duration::from_millis(600),
-                        },
-                    ),
-                )
-                .insert(ExorcismCard)
-                .with_children(|parent| {
-                    parent
-                        .spawn((
-                            Text("EXORCISED\n\nR to restart\nZ to undo".to_string()),
-                            TextFont::from_font(
-                                assets.load("fonts/WayfarersToyBoxRegular-gxxER.ttf"),
-                            ),
-                            TextColor(Color::WHITE),
-                        ))
-                        .insert(FontScale::from(FontSize::Medium));
-                });
-        } else if *state != WilloState::Dead && *last_state == WilloState::Dead {
-            // Player just un-died
-            if let Ok(entity) = death_cards.single() {
-                commands.entity(entity).despawn();
-            }
+                    },
+                    EaseFunction::QuadraticOut,
+                    EasingType::Once {
+                        duration: Duration::from_millis(600),
+                    },
+                ),
+            )
+            .insert(ExorcismCard)
+            .with_children(|parent| {
+                parent
+                    .spawn((
+                        Text("EXORCISED\n\nR to restart\nZ to undo".to_string()),
+                        TextFont::from_font(assets.load("fonts/WayfarersToyBoxRegular-gxxER.ttf")),
+                        TextColor(Color::WHITE),
+                    ))
+                    .insert(FontScale::from(FontSize::Medium));
+            });
+    } else if !any_dead && *any_was_dead {
+        // Player just un-died
+        if let Ok(entity) = death_cards.single() {
+            commands.entity(entity).despawn();
         }
-
-        *last_state = *state;
     }
+
+    *any_was_dead = any_dead;
 }
 
 fn make_exorcism_card_visible(mut ui_query: Query<&mut Visibility, Added<ExorcismCard>>) {
@@ -153,3 +232,40 @@ fn make_exorcism_card_visible(mut ui_query: Query<&mut Visibility, Added<Exorcis
         *visibility = Visibility::Inherited;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volatile_tiles_take_their_material_from_settings() {
+        let mut world = World::new();
+        world.insert_resource(VolatileTileSettings(HashMap::from([(
+            5,
+            "Lava".to_string(),
+        )])));
+
+        let lava = world
+            .spawn(ExorcismTileBundle {
+                volatile_tile: VolatileTile(5),
+                ..default()
+            })
+            .id();
+
+        let unknown = world
+            .spawn(ExorcismTileBundle {
+                volatile_tile: VolatileTile(6),
+                ..default()
+            })
+            .id();
+
+        assert_eq!(
+            world.get::<VolatileMaterial>(lava),
+            Some(&VolatileMaterial::new("Lava"))
+        );
+        assert_eq!(
+            world.get::<VolatileMaterial>(unknown),
+            Some(&VolatileMaterial::default())
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Plugin providing functionality for patrolling exorcists.
+//!
+//! Exorcists walk along a path, defined in LDtk by the "Path" points field, one step per turn.
+//! They return to where they started once they reach the end of the path, and then repeat it.
+//! Exorcists can be blocked by gravestones and walls, but pass through Willo, killing them.
+//! Willo is also killed when their paths cross during a move, like when they swap places.
+use crate::{
+    graveyard::{
+        collision::exorcist_collision_layers,
+        exorcism::{ExorcismEvent, ExorcismSets},
+        turn::TurnSets,
+        willo::WilloState,
+    },
+    history::{History, HistoryPlugin},
+    sokoban::{CollisionLayers, Direction, LeaveEvent, SokobanBlock, SokobanCommands, SokobanSets},
+    utils::any_match_filter,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use std::collections::HashMap;
+
+/// Plugin providing functionality for patrolling exorcists.
+pub struct ExorcistPlugin;
+
+impl Plugin for ExorcistPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HistoryPlugin::<Patrol, _>::run_in_state(
+            GameState::Graveyard,
+        ))
+        .add_systems(
+            Update,
+            (
+                anchor_patrols,
+                step_patrols.in_set(TurnSets::ActorStep),
+                check_exorcist_death
+                    .run_if(
+                        any_match_filter::<(
+                            Or<(With<Exorcist>, With<WilloState>)>,
+                            Changed<GridCoords>,
+                        )>,
+                    )
+                    .in_set(ExorcismSets::CheckDeath)
+                    .after(SokobanSets::MoveResolution),
+            )
+                .run_if(in_state(GameState::Graveyard)),
+        )
+        .register_ldtk_entity::<ExorcistBundle>("Exorcist");
+    }
+}
+
+/// Component that marks exorcists.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+pub struct Exorcist;
+
+/// Component defining the path that an entity patrols, one step per turn.
+///
+/// The waypoints are relative to the entity's starting position until the patrol is anchored.
+/// The first waypoint is always the starting position, so the patrol loops back to it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Component)]
+pub struct Patrol {
+    waypoints: Vec<IVec2>,
+    target: usize,
+}
+
+impl From<&EntityInstance> for Patrol {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        // LDtk points are in LDtk grid space, where y points down.
+        let waypoints = std::iter::once(IVec2::ZERO)
+            .chain(
+                entity_instance
+                    .get_maybe_points_field("Path")
+                    .expect("exorcists should have a path")
+                    .iter()
+                    .flatten()
+                    .map(|point| {
+                        let offset = *point - entity_instance.grid;
+                        IVec2::new(offset.x, -offset.y)
+                    }),
+            )
+            .collect();
+
+        Patrol {
+            waypoints,
+            target: 0,
+        }
+    }
+}
+
+impl Patrol {
+    /// Returns the direction of the next step from the given position, advancing the patrol's
+    /// target waypoint if it has been reached.
+    fn next_step(&mut self, position: IVec2) -> Option<Direction> {
+        if self.waypoints.is_empty() {
+            return None;
+        }
+
+        if self.waypoints[self.target] == position {
+            self.target = (self.target + 1) % self.waypoints.len();
+        }
+
+        let delta = self.waypoints[self.target] - position;
+
+        match (delta.x.signum(), delta.y.signum()) {
+            (1, _) => Some(Direction::Right),
+            (-1, _) => Some(Direction::Left),
+            (_, 1) => Some(Direction::Up),
+            (_, -1) => Some(Direction::Down),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct ExorcistBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    history: History<GridCoords>,
+    #[with(SokobanBlock::new_weak)]
+    sokoban_block: SokobanBlock,
+    #[with(exorcist_collision_layers)]
+    collision_layers: CollisionLayers,
+    exorcist: Exorcist,
+    #[from_entity_instance]
+    patrol: Patrol,
+    patrol_history: History<Patrol>,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+/// Makes the waypoints of newly spawned patrols absolute.
+fn anchor_patrols(mut patrols: Query<(&GridCoords, &mut Patrol), Added<Patrol>>) {
+    for (grid_coords, mut patrol) in patrols.iter_mut() {
+        let origin = IVec2::from(*grid_coords);
+        patrol
+            .waypoints
+            .iter_mut()
+            .for_each(|waypoint| *waypoint += origin);
+    }
+}
+
+fn step_patrols(
+    mut patrols: Query<(Entity, &GridCoords, &mut Patrol)>,
+    mut sokoban_commands: SokobanCommands<Direction>,
+) {
+    for (entity, grid_coords, mut patrol) in patrols.iter_mut() {
+        if let Some(direction) = patrol.next_step(IVec2::from(*grid_coords)) {
+            sokoban_commands.move_block(entity, direction);
+        }
+    }
+}
+
+/// Returns `true` if a willo and an exorcist met while moving along the given paths.
+///
+/// Each path lists the tiles left during the last move resolution, in order, followed by the
+/// final tile.
+/// They meet if they end on the same tile, or if either entered a tile the other occupied at some
+/// point of the resolution.
+fn paths_cross(willo_path: &[GridCoords], exorcist_path: &[GridCoords]) -> bool {
+    let enters = |path: &[GridCoords], other_path: &[GridCoords]| {
+        path.iter()
+            .skip(1)
+            .any(|grid_coords| other_path.contains(grid_coords))
+    };
+
+    willo_path.last() == exorcist_path.last()
+        || enters(willo_path, exorcist_path)
+        || enters(exorcist_path, willo_path)
+}
+
+fn check_exorcist_death(
+    mut willo_query: Query<(Entity, &mut WilloState, &GridCoords)>,
+    exorcists: Query<(Entity, &GridCoords), With<Exorcist>>,
+    mut leave_events: EventReader<LeaveEvent>,
+    mut death_event_writer: EventWriter<ExorcismEvent>,
+) {
+    let mut left_tiles: HashMap<Entity, Vec<GridCoords>> = HashMap::new();
+    for LeaveEvent {
+        entity,
+        grid_coords,
+    } in leave_events.read()
+    {
+        left_tiles.entry(*entity).or_default().push(*grid_coords);
+    }
+
+    let path = |entity: Entity, grid_coords: &GridCoords| -> Vec<GridCoords> {
+        left_tiles
+            .get(&entity)
+            .into_iter()
+            .flatten()
+            .chain([grid_coords])
+            .copied()
+            .collect()
+    };
+
+    let exorcist_paths: Vec<_> = exorcists
+        .iter()
+        .map(|(entity, grid_coords)| path(entity, grid_coords))
+        .collect();
+
+    for (willo_entity, mut willo, willo_grid_coords) in willo_query.iter_mut() {
+        let willo_path = path(willo_entity, willo_grid_coords);
+
+        let caught = exorcist_paths
+            .iter()
+            .any(|exorcist_path| paths_cross(&willo_path, exorcist_path));
+
+        if caught && *willo != WilloState::Dead {
+            *willo = WilloState::Dead;
+            death_event_writer.write(ExorcismEvent { willo_entity });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graveyard::{
+            collision::{gravestone_collision_layers, willo_collision_layers},
+            turn::TurnPlugin,
+        },
+        sokoban::{SokobanCommand, SokobanPlugin},
+    };
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_event::<ExorcismEvent>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                TurnPlugin,
+                ExorcistPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    fn spawn_willo(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((
+                grid_coords,
+                WilloState::Waiting,
+                SokobanBlock::Dynamic,
+                willo_collision_layers(&EntityInstance::default()),
+            ))
+            .id()
+    }
+
+    fn spawn_exorcist(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((
+                grid_coords,
+                Exorcist,
+                SokobanBlock::Weak,
+                exorcist_collision_layers(&EntityInstance::default()),
+            ))
+            .id()
+    }
+
+    fn move_block(app: &mut App, entity: Entity, direction: Direction) {
+        app.world_mut()
+            .send_event(SokobanCommand::Move { entity, direction });
+    }
+
+    #[test]
+    fn patrol_walks_waypoints_and_loops() {
+        let mut patrol = Patrol {
+            waypoints: vec![IVec2::new(0, 0), IVec2::new(2, 0), IVec2::new(2, 1)],
+            target: 0,
+        };
+
+        let mut position = IVec2::new(0, 0);
+        let mut steps = Vec::new();
+
+        for _ in 0..6 {
+            let direction = patrol.next_step(position).unwrap();
+            position = position + &direction;
+            steps.push(direction);
+        }
+
+        assert_eq!(
+            steps,
+            vec![
+                Direction::Right,
+                Direction::Right,
+                Direction::Up,
+                Direction::Left,
+                Direction::Left,
+                Direction::Down,
+            ]
+        );
+        assert_eq!(position, IVec2::new(0, 0));
+    }
+
+    #[test]
+    fn exorcist_walking_into_willo_kills_them() {
+        let mut app = app_setup();
+
+        let willo = spawn_willo(&mut app, GridCoords::new(1, 0));
+        let exorcist = spawn_exorcist(&mut app, GridCoords::new(0, 0));
+        app.update();
+
+        move_block(&mut app, exorcist, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            *app.world().get::<GridCoords>(willo).unwrap(),
+            GridCoords::new(1, 0)
+        );
+        assert_eq!(
+            *app.world().get::<GridCoords>(exorcist).unwrap(),
+            GridCoords::new(1, 0)
+        );
+        assert_eq!(
+            *app.world().get::<WilloState>(willo).unwrap(),
+            WilloState::Dead
+        );
+    }
+
+    #[test]
+    fn willo_swapping_places_with_exorcist_dies() {
+        let mut app = app_setup();
+
+        let willo = spawn_willo(&mut app, GridCoords::new(0, 0));
+        let exorcist = spawn_exorcist(&mut app, GridCoords::new(1, 0));
+        app.update();
+
+        move_block(&mut app, willo, Direction::Right);
+        move_block(&mut app, exorcist, Direction::Left);
+        app.update();
+
+        assert_eq!(
+            *app.world().get::<GridCoords>(willo).unwrap(),
+            GridCoords::new(1, 0)
+        );
+        assert_eq!(
+            *app.world().get::<WilloState>(willo).unwrap(),
+            WilloState::Dead
+        );
+    }
+
+    #[test]
+    fn willo_entering_a_tile_the_exorcist_leaves_dies() {
+        let mut app = app_setup();
+
+        let willo = spawn_willo(&mut app, GridCoords::new(0, 0));
+        let exorcist = spawn_exorcist(&mut app, GridCoords::new(1, 0));
+        app.update();
+
+        move_block(&mut app, willo, Direction::Right);
+        move_block(&mut app, exorcist, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            *app.world().get::<GridCoords>(exorcist).unwrap(),
+            GridCoords::new(2, 0)
+        );
+        assert_eq!(
+            *app.world().get::<WilloState>(willo).unwrap(),
+            WilloState::Dead
+        );
+    }
+
+    #[test]
+    fn exorcists_cant_push_gravestones() {
+        let mut app = app_setup();
+
+        let gravestone = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(1, 0),
+                SokobanBlock::Dynamic,
+                gravestone_collision_layers(&EntityInstance::default()),
+            ))
+            .id();
+        let exorcist = spawn_exorcist(&mut app, GridCoords::new(0, 0));
+        let willo = spawn_willo(&mut app, GridCoords::new(3, 3));
+        app.update();
+
+        move_block(&mut app, exorcist, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            *app.world().get::<GridCoords>(exorcist).unwrap(),
+            GridCoords::new(0, 0)
+        );
+        assert_eq!(
+            *app.world().get::<GridCoords>(gravestone).unwrap(),
+            GridCoords::new(1, 0)
+        );
+        assert_eq!(
+            *app.world().get::<WilloState>(willo).unwrap(),
+            WilloState::Waiting
+        );
+    }
+
+    #[test]
+    fn willo_cant_push_exorcists() {
+        let mut app = app_setup();
+
+        let willo = spawn_willo(&mut app, GridCoords::new(0, 0));
+        let exorcist = spawn_exorcist(&mut app, GridCoords::new(2, 0));
+        app.update();
+
+        // willo walks up to the exorcist, leaving them where they are
+        move_block(&mut app, willo, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            *app.world().get::<WilloState>(willo).unwrap(),
+            WilloState::Waiting
+        );
+
+        move_block(&mut app, willo, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            *app.world().get::<GridCoords>(exorcist).unwrap(),
+            GridCoords::new(2, 0)
+        );
+        assert_eq!(
+            *app.world().get::<WilloState>(willo).unwrap(),
+            WilloState::Dead
+        );
+    }
+}
@@ -0,0 +1,46 @@
+//! Plugin providing functionality for one-way gates.
+//!
+//! Gates can only be entered by moving in their direction, and block movement otherwise.
+use crate::sokoban::{Direction, SokobanBlock};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+/// Plugin providing functionality for one-way gates.
+///
+/// Gates can only be entered by moving in their direction, and block movement otherwise.
+pub struct GatePlugin;
+
+impl Plugin for GatePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<GateBundle>("UpGate")
+            .register_ldtk_entity::<GateBundle>("LeftGate")
+            .register_ldtk_entity::<GateBundle>("DownGate")
+            .register_ldtk_entity::<GateBundle>("RightGate");
+    }
+}
+
+fn gate_block(entity_instance: &EntityInstance) -> SokobanBlock {
+    let direction = if entity_instance.identifier.contains("Up") {
+        Direction::Up
+    } else if entity_instance.identifier.contains("Left") {
+        Direction::Left
+    } else if entity_instance.identifier.contains("Down") {
+        Direction::Down
+    } else if entity_instance.identifier.contains("Right") {
+        Direction::Right
+    } else {
+        panic!("Gate Identifier should contain direction")
+    };
+
+    SokobanBlock::Gate(direction)
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct GateBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[with(gate_block)]
+    sokoban_block: SokobanBlock,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
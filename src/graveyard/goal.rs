@@ -1,15 +1,29 @@
-//! Plugin providing functionality for goal tiles with victory logic and goal ghost visuals.
+//! Plugin providing functionality for goal tiles and goal ghost visuals.
 use crate::{
-    graveyard::{exorcism::ExorcismSets, gravestone::GraveId},
-    level_transition::TransitionTo,
-    AssetHolder, GameState,
+    graveyard::{
+        carry::Carried,
+        exorcism::ExorcismSets,
+        gravestone::{grave_id_key_icon, GraveId, GravestoneAssets},
+        multiplayer::Unmirrored,
+    },
+    GameState,
 };
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
+use leafwing_input_manager::prelude::*;
 use rand::Rng;
 use std::{ops::Range, time::Duration};
 
-/// Plugin providing functionality for goal tiles with victory logic and goal ghost visuals.
+/// Sets used by goal systems.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum GoalSets {
+    /// Set for the system that updates whether each goal is met.
+    TrackGoals,
+}
+
+/// Plugin providing functionality for goal tiles and goal ghost visuals.
+///
+/// Whether the met goals complete the level is decided by the level's completion condition.
 pub struct GoalPlugin;
 
 impl Plugin for GoalPlugin {
@@ -20,8 +34,9 @@ impl Plugin for GoalPlugin {
                 Update,
                 (
                     spawn_goal_ghosts.run_if(in_state(GameState::LevelTransition)),
-                    check_goal
+                    track_goals
                         .run_if(in_state(GameState::Graveyard))
+                        .in_set(GoalSets::TrackGoals)
                         .after(ExorcismSets::CheckDeath),
                     goal_ghost_animation.run_if(not(in_state(GameState::AssetLoading))),
                     goal_ghost_event_sugar.run_if(not(in_state(GameState::AssetLoading))),
@@ -32,19 +47,62 @@ impl Plugin for GoalPlugin {
 }
 
 /// Component that marks goal tiles and stores whether or not it is currently "satisfied".
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
-struct Goal {
+///
+/// Goals can be restricted to particular gravestones via the optional "Accepts" LDtk field.
+/// If no gravestones are listed, any gravestone satisfies the goal.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Goal {
     met: bool,
+    accepts: Vec<GraveId>,
+}
+
+impl Goal {
+    /// Returns `true` if an accepted gravestone is currently on this goal.
+    pub fn is_met(&self) -> bool {
+        self.met
+    }
+
+    fn accepts(&self, grave_id: &GraveId) -> bool {
+        self.accepts.is_empty() || self.accepts.contains(grave_id)
+    }
+}
+
+impl From<&EntityInstance> for Goal {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let accepts = entity_instance
+            .get_maybe_enums_field("Accepts")
+            .map(|grave_ids| {
+                grave_ids
+                    .iter()
+                    .flatten()
+                    .map(|grave_id| {
+                        GraveId::from_identifier(grave_id)
+                            .unwrap_or_else(|| panic!("encountered bad goal grave id: {grave_id}"))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Goal {
+            met: false,
+            accepts,
+        }
+    }
 }
 
 /// Event that fires when a goal's state changes.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Event)]
-enum GoalEvent {
+pub enum GoalEvent {
+    /// An accepted gravestone was moved onto the goal.
     Met {
+        /// The goal that was met.
         goal_entity: Entity,
+        /// The gravestone that met the goal.
         stone_entity: Entity,
     },
+    /// The goal is no longer covered by an accepted gravestone.
     UnMet {
+        /// The goal that is no longer met.
         goal_entity: Entity,
     },
 }
@@ -53,13 +111,14 @@ enum GoalEvent {
 struct GoalBundle {
     #[grid_coords]
     grid_coords: GridCoords,
+    #[from_entity_instance]
     goal: Goal,
     #[sprite_sheet]
     sprite_sheet_bundle: Sprite,
 }
 
 /// Resource for defining the visual behavior of goal ghosts.
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Resource)]
+#[derive(Clone, PartialEq, Debug, Resource)]
 struct GoalGhostSettings {
     no_turn_length: Range<usize>,
     turn_length: Range<usize>,
@@ -72,6 +131,9 @@ struct GoalGhostSettings {
     num_columns: usize,
     num_rows: usize,
     atlas: Option<Handle<TextureAtlasLayout>>,
+    icon_translation: Vec3,
+    icon_spacing: f32,
+    icon_scale: f32,
 }
 
 impl Default for GoalGhostSettings {
@@ -88,6 +150,9 @@ impl Default for GoalGhostSettings {
             num_columns: 10,
             num_rows: 5,
             atlas: None,
+            icon_translation: Vec3::new(0., 14., 0.1),
+            icon_spacing: 9.,
+            icon_scale: 0.5,
         }
     }
 }
@@ -150,29 +215,15 @@ fn range_chance(range: &Range<usize>, current: usize) -> f32 {
     ((current as f32 - range.start as f32) / (range.end as f32 - range.start as f32)).clamp(0., 1.)
 }
 
-fn check_goal(
-    mut commands: Commands,
-    mut goal_query: Query<(Entity, &mut Goal, &GridCoords), With<Goal>>,
-    block_query: Query<(Entity, &GridCoords), With<GraveId>>,
+fn track_goals(
+    mut goal_query: Query<(Entity, &mut Goal, &GridCoords)>,
+    block_query: Query<(Entity, &GridCoords, &GraveId), Without<Carried>>,
     mut goal_events: EventWriter<GoalEvent>,
-    mut next_state: ResMut<NextState<GameState>>,
-    level_selection: Res<LevelSelection>,
-    ldtk_assets: Res<Assets<LdtkProject>>,
-    asset_holder: Res<AssetHolder>,
 ) {
-    // If the goal is not loaded for whatever reason (for example when hot-reloading levels),
-    // the goal will automatically be "met", loading the next level.
-    // This if statement prevents that.
-    if goal_query.iter().count() == 0 {
-        return;
-    }
-
-    let mut level_goal_met = true;
-
     for (goal_entity, mut goal, goal_grid_coords) in goal_query.iter_mut() {
         let mut goal_met = false;
-        for (stone_entity, block_grid_coords) in block_query.iter() {
-            if goal_grid_coords == block_grid_coords {
+        for (stone_entity, block_grid_coords, grave_id) in block_query.iter() {
+            if goal_grid_coords == block_grid_coords && goal.accepts(grave_id) {
                 goal_met = true;
 
                 if !goal.met {
@@ -187,40 +238,13 @@ fn check_goal(
                 break;
             }
         }
-        if !goal_met {
-            level_goal_met = false;
-
-            if goal.met {
-                goal_events.write(GoalEvent::UnMet { goal_entity });
-                goal.met = false;
-            }
-        }
-    }
-
-    if level_goal_met {
-        next_state.set(GameState::LevelTransition);
-
-        if let Some(ldtk_asset) = ldtk_assets.get(&asset_holder.ldtk) {
-            let selected_level = ldtk_asset
-                .find_raw_level_by_level_selection(&level_selection)
-                .expect("level should exist in project");
-            let level_index = ldtk_asset
-                .get_level_metadata_by_iid(&selected_level.iid)
-                .expect("level should exist in project")
-                .indices()
-                .level;
-
-            // Currently this doesn't have a time buffer like it used to.
-            // This will change as we make a more elaborate level transition workflow.
-            commands.insert_resource(TransitionTo(LevelSelection::index(level_index + 1)));
+        if !goal_met && goal.met {
+            goal_events.write(GoalEvent::UnMet { goal_entity });
+            goal.met = false;
         }
-
-        commands.spawn((
-            AudioPlayer::new(asset_holder.victory_sound.clone()),
-            PlaybackSettings::DESPAWN,
-        ));
     }
 }
+
 fn goal_ghost_event_sugar(
     mut goal_ghost_query: Query<&mut GoalGhostAnimation>,
     mut goal_events: EventReader<GoalEvent>,
@@ -355,12 +379,14 @@ fn goal_ghost_animation(
 
 fn spawn_goal_ghosts(
     mut commands: Commands,
-    goals: Query<Entity, Added<Goal>>,
+    goals: Query<(Entity, &Goal), Added<Goal>>,
     mut goal_ghost_settings: ResMut<GoalGhostSettings>,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    gravestone_assets: Res<GravestoneAssets>,
+    input_map: Res<InputMap<GraveId>>,
 ) {
-    for goal_entity in goals.iter() {
+    for (goal_entity, goal) in goals.iter() {
         let image_handle = asset_server.load("textures/animations/goal_ghost-Sheet.png");
 
         let atlas_handle = match &goal_ghost_settings.atlas {
@@ -393,8 +419,105 @@ fn spawn_goal_ghosts(
                 goal_entity,
                 Timer::new(goal_ghost_settings.frame_duration, TimerMode::Repeating),
             ))
+            .with_children(|parent| {
+                // restriction icons, centered in a row above the ghost
+                let icon_count = goal.accepts.len() as f32;
+                for (i, grave_id) in goal.accepts.iter().enumerate() {
+                    if let Some(icon) = grave_id_key_icon(grave_id, &input_map, &gravestone_assets)
+                    {
+                        let x =
+                            (i as f32 - (icon_count - 1.) / 2.) * goal_ghost_settings.icon_spacing;
+                        parent.spawn((
+                            icon,
+                            Transform::from_translation(
+                                goal_ghost_settings.icon_translation + Vec3::X * x,
+                            )
+                            .with_scale(Vec3::splat(goal_ghost_settings.icon_scale)),
+                            Unmirrored,
+                        ));
+                    }
+                }
+            })
             .id();
 
         commands.entity(goal_entity).add_child(ghost_entity);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .init_resource::<Time>()
+            .add_plugins(GoalPlugin)
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    fn met_goals(app: &mut App) -> Vec<(Entity, Entity)> {
+        app.world_mut()
+            .resource_mut::<Events<GoalEvent>>()
+            .drain()
+            .filter_map(|event| match event {
+                GoalEvent::Met {
+                    goal_entity,
+                    stone_entity,
+                } => Some((goal_entity, stone_entity)),
+                GoalEvent::UnMet { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn restricted_goal_is_only_met_by_accepted_gravestones() {
+        let mut app = app_setup();
+        let goal = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 0),
+                Goal {
+                    met: false,
+                    accepts: vec![GraveId('W')],
+                },
+            ))
+            .id();
+        let stone = app
+            .world_mut()
+            .spawn((GridCoords::new(0, 0), GraveId('Q')))
+            .id();
+        app.update();
+
+        assert!(met_goals(&mut app).is_empty());
+        assert!(!app.world().get::<Goal>(goal).unwrap().is_met());
+
+        app.world_mut().entity_mut(stone).insert(GraveId('W'));
+        app.update();
+
+        assert_eq!(met_goals(&mut app), vec![(goal, stone)]);
+        assert!(app.world().get::<Goal>(goal).unwrap().is_met());
+    }
+
+    #[test]
+    fn unrestricted_goal_is_met_by_any_gravestone() {
+        let mut app = app_setup();
+        let goal = app
+            .world_mut()
+            .spawn((GridCoords::new(0, 0), Goal::default()))
+            .id();
+        let stone = app
+            .world_mut()
+            .spawn((GridCoords::new(0, 0), GraveId('Q')))
+            .id();
+        app.update();
+
+        assert_eq!(met_goals(&mut app), vec![(goal, stone)]);
+    }
+}
@@ -3,20 +3,25 @@
 //! Gravestones are sokoban blocks that
 //! - interact with goals to complete levels
 //! - interact with the movement table to alter Willo's abilities
+//! - can be carried by Willo
 use crate::{
     graveyard::{
-        volatile::{Sublimation, Volatile},
+        carry::Carried,
+        collision::gravestone_collision_layers,
+        multiplayer::{GraveInput, Player, Unmirrored},
+        volatile::{Sublimation, Volatile, VolatileMaterial},
         willo::{WilloSets, WilloState},
     },
     history::{FlushHistoryCommands, History, HistoryCommands},
-    sokoban::SokobanBlock,
+    sokoban::{CollisionLayers, SokobanBlock},
     ui::{action::UiActionPlugin, button_prompt::ButtonPromptPlugin},
+    utils::any_match_filter,
     GameState,
 };
 use bevy::{prelude::*, reflect::Enum};
 use bevy_asset_loader::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
-use leafwing_input_manager::prelude::*;
+use leafwing_input_manager::{prelude::*, InputControlKind};
 use rand::{distributions::WeightedIndex, prelude::*};
 use serde::{Deserialize, Serialize};
 use std::{fs::File, io::BufReader, ops::Range};
@@ -32,6 +37,13 @@ impl Plugin for GravestonePlugin {
     fn build(&self, app: &mut App) {
         let asset_folder = app.get_added_plugins::<AssetPlugin>()[0].file_path.clone();
 
+        let grave_layout = load_grave_layout_settings(asset_folder.clone())
+            .expect("unable to load grave layout settings");
+
+        for (grave_id, _) in grave_layout.slots() {
+            app.register_ldtk_entity::<GravestoneBundle>(&grave_id.0.to_string());
+        }
+
         app.add_plugins((
             InputManagerPlugin::<GraveId>::default(),
             UiActionPlugin::<GraveId>::new(),
@@ -39,6 +51,7 @@ impl Plugin for GravestonePlugin {
         ))
         .init_resource::<ActionState<GraveId>>()
         .init_resource::<GravestoneSettings>()
+        .insert_resource(grave_layout)
         .insert_resource(
             load_gravestone_control_settings(asset_folder)
                 .expect("unable to load gravestone control settings"),
@@ -49,19 +62,15 @@ impl Plugin for GravestonePlugin {
                 spawn_gravestone_body.run_if(in_state(GameState::LevelTransition)),
                 sublimate_gravestones
                     .run_if(in_state(GameState::Graveyard))
+                    .run_if(any_match_filter::<Changed<Volatile>>)
+                    .after(FlushHistoryCommands)
                     .after(Sublimation),
                 gravestone_input
                     .run_if(in_state(GameState::Graveyard))
                     .in_set(WilloSets::Input)
                     .before(FlushHistoryCommands),
             ),
-        )
-        .register_ldtk_entity::<GravestoneBundle>("Q")
-        .register_ldtk_entity::<GravestoneBundle>("W")
-        .register_ldtk_entity::<GravestoneBundle>("E")
-        .register_ldtk_entity::<GravestoneBundle>("A")
-        .register_ldtk_entity::<GravestoneBundle>("S")
-        .register_ldtk_entity::<GravestoneBundle>("D");
+        );
     }
 }
 
@@ -95,37 +104,84 @@ impl Default for GravestoneSettings {
     }
 }
 
-/// Component that marks gravestones and associates them with an action.
+/// Component that marks gravestones and associates them with a grave slot.
 ///
 /// Also acts as the grave-action itself by implementing Actionlike.
-#[derive(
-    Actionlike,
-    Copy,
-    Clone,
-    Default,
-    PartialEq,
-    Eq,
-    Debug,
-    Hash,
-    Component,
-    Serialize,
-    Deserialize,
-    Reflect,
-)]
-pub enum GraveId {
-    /// Gravestone/action that applies to "northwesty" buttons like Q and Left Bumper.
-    Northwest,
-    #[default]
-    /// Gravestone/action that applies to "northy" buttons like W and Triangle.
-    North,
-    /// Gravestone/action that applies to "northeasty" buttons like E and Right Bumper.
-    Northeast,
-    /// Gravestone/action that applies to "westy" buttons like A and Square.
-    West,
-    /// Gravestone/action that applies to "southy" buttons like S and X/Cross.
-    South,
-    /// Gravestone/action that applies to "northy" buttons like D and Circle.
-    East,
+/// Each slot is named by a single character, like `'Q'`, which is also the LDtk identifier of its
+/// gravestones.
+/// The available slots are defined by the [GraveLayout].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Component, Serialize, Deserialize, Reflect)]
+pub struct GraveId(pub char);
+
+impl Actionlike for GraveId {
+    fn input_control_kind(&self) -> InputControlKind {
+        InputControlKind::Button
+    }
+}
+
+/// Resource defining the available grave slots and how they're arranged in the control display.
+///
+/// Each row is a string of grave slot identifiers, read left to right, and spaces leave gaps.
+/// The default layout is the classic QWE/ASD arrangement.
+/// A 3x3 numpad-style layout could be configured in `settings/grave_layout.json` with
+/// `{ "rows": ["QWE", "ASD", "ZXC"] }`, alongside bindings for those slots in the gravestone
+/// control settings.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize, Resource)]
+pub struct GraveLayout {
+    rows: Vec<String>,
+}
+
+impl Default for GraveLayout {
+    fn default() -> Self {
+        GraveLayout {
+            rows: vec!["QWE".to_string(), "ASD".to_string()],
+        }
+    }
+}
+
+impl GraveLayout {
+    /// Returns each grave slot along with its column and row, in reading order.
+    pub fn slots(&self) -> impl Iterator<Item = (GraveId, UVec2)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(row, identifiers)| {
+            identifiers
+                .chars()
+                .enumerate()
+                .filter(|(_, identifier)| !identifier.is_whitespace())
+                .map(move |(column, identifier)| {
+                    (GraveId(identifier), UVec2::new(column as u32, row as u32))
+                })
+        })
+    }
+
+    /// Returns the number of columns and rows in the layout.
+    pub fn size(&self) -> UVec2 {
+        UVec2::new(
+            self.rows
+                .iter()
+                .map(|identifiers| identifiers.chars().count() as u32)
+                .max()
+                .unwrap_or_default(),
+            self.rows.len() as u32,
+        )
+    }
+}
+
+fn load_grave_layout_settings(asset_folder: String) -> std::io::Result<GraveLayout> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Ok(serde_json::from_reader(BufReader::new(File::open(
+            format!("{asset_folder}/../settings/grave_layout.json"),
+        )?))?)
+    }
+
+    // placed in a `#[cfg]` block rather than `if cfg!` so that changes to the file don't
+    // recompile non-wasm builds.
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(serde_json::from_str(include_str!(
+            "../../settings/grave_layout.json"
+        ))?)
+    }
 }
 
 fn load_gravestone_control_settings(asset_folder: String) -> std::io::Result<InputMap<GraveId>> {
@@ -146,31 +202,88 @@ fn load_gravestone_control_settings(asset_folder: String) -> std::io::Result<Inp
     }
 }
 
+impl GraveId {
+    /// Returns the [GraveId] for the given LDtk identifier, like "Q" for `GraveId('Q')`.
+    ///
+    /// Identifiers must be a single character.
+    pub fn from_identifier(identifier: &str) -> Option<GraveId> {
+        let mut chars = identifier.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(identifier), None) if !identifier.is_whitespace() => Some(GraveId(identifier)),
+            _ => None,
+        }
+    }
+}
+
 impl From<&EntityInstance> for GraveId {
     fn from(entity_instance: &EntityInstance) -> Self {
-        match entity_instance.identifier.as_ref() {
-            "Q" => GraveId::Northwest,
-            "W" => GraveId::North,
-            "E" => GraveId::Northeast,
-            "A" => GraveId::West,
-            "S" => GraveId::South,
-            "D" => GraveId::East,
-            g => panic!("encountered bad gravestone identifier: {g}"),
-        }
+        let g = &entity_instance.identifier;
+        GraveId::from_identifier(g)
+            .unwrap_or_else(|| panic!("encountered bad gravestone identifier: {g}"))
     }
 }
 
-#[derive(Clone, Default, Bundle, LdtkEntity)]
+/// Returns a sprite of the keyboard key bound to the given [GraveId], if there is one.
+pub fn grave_id_key_icon(
+    grave_id: &GraveId,
+    input_map: &InputMap<GraveId>,
+    assets: &GravestoneAssets,
+) -> Option<Sprite> {
+    input_map
+        .get_buttonlike(grave_id)
+        .iter()
+        .flat_map(|inputs| inputs.iter())
+        .find_map(|i| i.clone().into_any().downcast::<KeyCode>().ok())
+        .map(|key_code| Sprite {
+            image: assets.key_code_icons.clone(),
+            texture_atlas: Some(TextureAtlas {
+                layout: assets.key_code_icons_layout.clone(),
+                index: key_code.variant_index(),
+            }),
+            ..default()
+        })
+}
+
+#[derive(Clone, Bundle)]
 struct GravestoneBundle {
-    #[grid_coords]
     grid_coords: GridCoords,
     history: History<GridCoords>,
-    #[with(SokobanBlock::new_dynamic)]
     sokoban_block: SokobanBlock,
-    #[from_entity_instance]
+    collision_layers: CollisionLayers,
     gravestone: GraveId,
     volatile: Volatile,
     volatile_history: History<Volatile>,
+    material: VolatileMaterial,
+    material_history: History<VolatileMaterial>,
+}
+
+fn gravestone_material(_: &EntityInstance) -> VolatileMaterial {
+    VolatileMaterial::new("Gravestone")
+}
+
+// implemented by hand, since the derive needs a default [GraveId], and there's no sensible one
+impl LdtkEntity for GravestoneBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlasLayout>,
+    ) -> Self {
+        GravestoneBundle {
+            grid_coords: GridCoords::from_entity_info(entity_instance, layer_instance),
+            history: History::default(),
+            sokoban_block: SokobanBlock::new_dynamic(entity_instance),
+            collision_layers: gravestone_collision_layers(entity_instance),
+            gravestone: GraveId::from(entity_instance),
+            volatile: Volatile::default(),
+            volatile_history: History::default(),
+            material: gravestone_material(entity_instance),
+            material_history: History::default(),
+        }
+    }
 }
 
 fn spawn_gravestone_body(
@@ -209,77 +322,108 @@ fn spawn_gravestone_body(
                 ));
 
                 // icon entity
-                if let Some(key_code) = input_map
-                    .get_buttonlike(grave_id)
-                    .iter()
-                    .flat_map(|inputs| inputs.iter())
-                    .find_map(|i| i.clone().into_any().downcast::<KeyCode>().ok())
-                {
+                if let Some(icon) = grave_id_key_icon(grave_id, &input_map, &assets) {
                     parent.spawn((
-                        Sprite {
-                            image: assets.key_code_icons.clone(),
-                            texture_atlas: Some(TextureAtlas {
-                                layout: assets.key_code_icons_layout.clone(),
-                                index: key_code.variant_index(),
-                            }),
-                            ..default()
-                        },
+                        icon,
                         Transform::from_translation(settings.icon_translation),
+                        Unmirrored,
                     ));
                 }
             });
     }
 }
 
+/// Keeps gravestones' visibility and sokoban blocks in line with their volatility.
+///
+/// Gravestones are only sokoban blocks while they're solid.
+/// Carried gravestones are left to the carry plugin, see [Carried].
 fn sublimate_gravestones(
     mut commands: Commands,
     mut gravestone_query: Query<
-        (Entity, &Volatile, &mut Visibility),
-        (With<GraveId>, Changed<Volatile>),
+        (Entity, &Volatile, &mut Visibility, Has<SokobanBlock>),
+        (With<GraveId>, Without<Carried>),
     >,
 ) {
-    for (gravestone_entity, volatile, mut visibility) in gravestone_query.iter_mut() {
+    for (gravestone_entity, volatile, mut visibility, is_block) in gravestone_query.iter_mut() {
         match volatile {
             Volatile::Solid => {
-                *visibility = Visibility::Inherited;
+                visibility.set_if_neq(Visibility::Inherited);
+            }
+            Volatile::Sublimated => {
+                visibility.set_if_neq(Visibility::Hidden);
+            }
+        }
+
+        if volatile.is_solid() {
+            if !is_block {
                 commands
                     .entity(gravestone_entity)
                     .insert(SokobanBlock::Dynamic);
             }
-            Volatile::Sublimated => {
-                *visibility = Visibility::Hidden;
-                commands.entity(gravestone_entity).remove::<SokobanBlock>();
-            }
+        } else if is_block {
+            commands.entity(gravestone_entity).remove::<SokobanBlock>();
         }
     }
 }
 
 fn gravestone_input(
-    mut willo_query: Query<&mut WilloState>,
-    grave_input: Res<ActionState<GraveId>>,
+    mut willo_query: Query<(&mut WilloState, Option<&Player>)>,
+    grave_input: GraveInput,
     mut history_commands: EventWriter<HistoryCommands>,
 ) {
-    for mut willo in willo_query.iter_mut() {
-        if *willo == WilloState::Waiting {
-            if grave_input.just_pressed(&GraveId::Northwest) {
-                history_commands.write(HistoryCommands::Record);
-                *willo = WilloState::RankMove(GraveId::Northwest)
-            } else if grave_input.just_pressed(&GraveId::North) {
-                history_commands.write(HistoryCommands::Record);
-                *willo = WilloState::RankMove(GraveId::North)
-            } else if grave_input.just_pressed(&GraveId::Northeast) {
-                history_commands.write(HistoryCommands::Record);
-                *willo = WilloState::RankMove(GraveId::Northeast)
-            } else if grave_input.just_pressed(&GraveId::West) {
-                history_commands.write(HistoryCommands::Record);
-                *willo = WilloState::RankMove(GraveId::West)
-            } else if grave_input.just_pressed(&GraveId::South) {
-                history_commands.write(HistoryCommands::Record);
-                *willo = WilloState::RankMove(GraveId::South)
-            } else if grave_input.just_pressed(&GraveId::East) {
-                history_commands.write(HistoryCommands::Record);
-                *willo = WilloState::RankMove(GraveId::East)
-            }
-        }
+    // willos perform grave actions together, so they must all be ready for the next one
+    if willo_query.is_empty()
+        || willo_query
+            .iter()
+            .any(|(willo, _)| *willo != WilloState::Waiting)
+    {
+        return;
+    }
+
+    let Some(grave_id) = grave_input.just_pressed() else {
+        return;
+    };
+
+    let mut controlled_willos = willo_query
+        .iter_mut()
+        .filter(|(_, player)| grave_input.controls(*player))
+        .peekable();
+
+    if controlled_willos.peek().is_some() {
+        history_commands.write(HistoryCommands::Record);
+    }
+
+    for (mut willo, _) in controlled_willos {
+        *willo = WilloState::RankMove(grave_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_slots_skip_gaps() {
+        let layout: GraveLayout = serde_json::from_str(r#"{ "rows": ["Q E", "ASDF"] }"#).unwrap();
+
+        assert_eq!(layout.size(), UVec2::new(4, 2));
+        assert_eq!(
+            layout.slots().collect::<Vec<_>>(),
+            vec![
+                (GraveId('Q'), UVec2::new(0, 0)),
+                (GraveId('E'), UVec2::new(2, 0)),
+                (GraveId('A'), UVec2::new(0, 1)),
+                (GraveId('S'), UVec2::new(1, 1)),
+                (GraveId('D'), UVec2::new(2, 1)),
+                (GraveId('F'), UVec2::new(3, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn identifiers_must_be_one_character() {
+        assert_eq!(GraveId::from_identifier("Q"), Some(GraveId('Q')));
+        assert_eq!(GraveId::from_identifier("QW"), None);
+        assert_eq!(GraveId::from_identifier(""), None);
     }
 }
@@ -2,26 +2,44 @@
 use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_ecs_ldtk::prelude::*;
 
-use crate::graveyard::{arrow_block::MovementTile, gravestone::GraveId};
+use crate::graveyard::{arrow_block::MovementTile, carry::Carrying, gravestone::GraveId};
 
 use super::volatile::Volatile;
 
 /// System param that can be used to find available movements.
 #[derive(Debug, SystemParam)]
 pub struct GravestoneMovementQueries<'w, 's> {
-    gravestones: Query<'w, 's, (&'static GridCoords, &'static GraveId, &'static Volatile)>,
+    gravestones: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static GridCoords,
+            &'static GraveId,
+            &'static Volatile,
+        ),
+    >,
+    carriers: Query<'w, 's, (&'static GridCoords, &'static Carrying)>,
     movement_tiles: Query<'w, 's, (&'static GridCoords, &'static MovementTile)>,
 }
 
 impl<'w, 's> GravestoneMovementQueries<'w, 's> {
     /// Resturns the movement associated with the given [`GraveId`], if it has one.
+    ///
+    /// A carried gravestone is treated as if it were at the position of the Willo carrying it.
     pub fn find_movement(&self, grave_id: &GraveId) -> Option<&MovementTile> {
         self.gravestones
             .iter()
-            .find(|(_, this_grave_id, volatile)| {
+            .find(|(_, _, this_grave_id, volatile)| {
                 **volatile == Volatile::Solid && &grave_id == this_grave_id
             })
-            .and_then(|(grid_coords, ..)| {
+            .map(|(entity, grid_coords, ..)| {
+                self.carriers
+                    .iter()
+                    .find(|(_, carrying)| ***carrying == Some(entity))
+                    .map_or(grid_coords, |(carrier_grid_coords, _)| carrier_grid_coords)
+            })
+            .and_then(|grid_coords| {
                 self.movement_tiles
                     .iter()
                     .find(|(this_grid_coords, _)| &grid_coords == this_grid_coords)
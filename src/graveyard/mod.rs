@@ -3,13 +3,29 @@
 //! So, the logic for core gameplay lives here.
 
 pub mod arrow_block;
+pub mod carry;
+pub mod collectible;
+pub mod collision;
+pub mod completion;
 pub mod control_display;
+pub mod conveyor;
+pub mod cracked;
+pub mod door;
 pub mod exorcism;
+pub mod exorcist;
+pub mod gate;
 pub mod goal;
 pub mod gravestone;
 pub mod gravestone_movement_queries;
 pub mod layer;
+pub mod multiplayer;
 pub mod out_of_bounds;
+pub mod pit;
+pub mod sticky_block;
+pub mod switch;
+pub mod teleporter;
+pub mod timed_volatile;
+pub mod turn;
 pub mod volatile;
 pub mod wall;
 pub mod willo;
@@ -43,9 +59,13 @@ impl Plugin for GraveyardPlugin {
             ))
             .init_resource::<ActionState<GraveyardAction>>()
             .insert_resource(
-                load_graveyard_control_settings(asset_folder)
+                load_graveyard_control_settings(asset_folder.clone())
                     .expect("unable to load gameplay control settings"),
             )
+            .insert_resource(
+                volatile::load_reaction_table(asset_folder)
+                    .expect("unable to load volatile reaction table"),
+            )
             .add_plugins((
                 control_display::ControlDisplayPlugin,
                 willo::WilloPlugin,
@@ -61,6 +81,23 @@ impl Plugin for GraveyardPlugin {
                 wind::WindPlugin,
                 out_of_bounds::OutOfBoundsPlugin,
                 arrow_block::ArrowBlockPlugin,
+                sticky_block::StickyBlockPlugin,
+                pit::PitPlugin,
+                teleporter::TeleporterPlugin,
+            ))
+            .add_plugins((
+                gate::GatePlugin,
+                turn::TurnPlugin,
+                exorcist::ExorcistPlugin,
+                timed_volatile::TimedVolatilePlugin,
+                completion::CompletionPlugin,
+                door::DoorPlugin,
+                switch::SwitchPlugin,
+                multiplayer::MultiplayerPlugin,
+                carry::CarryPlugin,
+                collectible::CollectiblePlugin,
+                cracked::CrackedPlugin,
+                conveyor::ConveyorPlugin,
             ))
             .add_systems(
                 Update,
@@ -81,6 +118,8 @@ pub enum GraveyardAction {
     Restart,
     /// Pause the graveyard state and open up the pause menu.
     Pause,
+    /// Pick up the gravestone Willo is facing, or put down the one they're carrying.
+    Carry,
 }
 
 fn load_graveyard_control_settings(
@@ -146,34 +185,71 @@ fn graveyard_input(
     mut rewind_settings: ResMut<RewindSettings>,
     time: Res<Time>,
 ) {
-    for mut willo in willo_query.iter_mut() {
-        if *willo == willo::WilloState::Waiting || *willo == willo::WilloState::Dead {
-            if gameplay_input.just_pressed(&GraveyardAction::Undo) {
-                history_commands.write(HistoryCommands::Rewind);
-                *willo = willo::WilloState::Waiting;
-                rewind_settings.hold_timer =
-                    Some(RewindTimer::new(rewind_settings.hold_range_millis.end));
-            } else if gameplay_input.pressed(&GraveyardAction::Undo) {
-                let range = rewind_settings.hold_range_millis.clone();
-                let acceleration = rewind_settings.hold_acceleration;
-
-                if let Some(RewindTimer { velocity, timer }) = &mut rewind_settings.hold_timer {
-                    *velocity = (*velocity - (acceleration * time.delta_secs()))
-                        .clamp(range.start as f32, range.end as f32);
-
-                    timer.tick(time.delta());
-
-                    if timer.just_finished() {
-                        history_commands.write(HistoryCommands::Rewind);
-                        *willo = willo::WilloState::Waiting;
-
-                        timer.set_duration(Duration::from_millis(*velocity as u64));
-                    }
-                }
-            } else if gameplay_input.just_pressed(&GraveyardAction::Restart) {
-                history_commands.write(HistoryCommands::Reset);
-                *willo = willo::WilloState::Waiting;
+    // with several willos, history is shared, so commands are only sent once all are idle
+    if willo_query.is_empty()
+        || willo_query
+            .iter()
+            .any(|willo| *willo != willo::WilloState::Waiting && *willo != willo::WilloState::Dead)
+    {
+        return;
+    }
+
+    let mut history_command = None;
+
+    if gameplay_input.just_pressed(&GraveyardAction::Undo) {
+        history_command = Some(HistoryCommands::Rewind);
+        rewind_settings.hold_timer = Some(RewindTimer::new(rewind_settings.hold_range_millis.end));
+    } else if gameplay_input.pressed(&GraveyardAction::Undo) {
+        let range = rewind_settings.hold_range_millis.clone();
+        let acceleration = rewind_settings.hold_acceleration;
+
+        if let Some(RewindTimer { velocity, timer }) = &mut rewind_settings.hold_timer {
+            *velocity = (*velocity - (acceleration * time.delta_secs()))
+                .clamp(range.start as f32, range.end as f32);
+
+            timer.tick(time.delta());
+
+            if timer.just_finished() {
+                history_command = Some(HistoryCommands::Rewind);
+
+                timer.set_duration(Duration::from_millis(*velocity as u64));
             }
         }
+    } else if gameplay_input.just_pressed(&GraveyardAction::Restart) {
+        history_command = Some(HistoryCommands::Reset);
+    }
+
+    if let Some(history_command) = history_command {
+        history_commands.write(history_command);
+
+        for mut willo in willo_query.iter_mut() {
+            *willo = willo::WilloState::Waiting;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::SpriteSheetAnimationPlugin;
+    use bevy::{input::InputPlugin, state::app::StatesPlugin};
+    use bevy_asset_loader::prelude::*;
+
+    #[test]
+    fn graveyard_schedule_builds() {
+        let mut app = App::new();
+
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            StatesPlugin,
+            InputPlugin,
+        ))
+        .init_state::<GameState>()
+        .add_loading_state(LoadingState::new(GameState::AssetLoading))
+        .add_plugins((GraveyardPlugin, SpriteSheetAnimationPlugin));
+
+        // panics if the graveyard's system ordering has a cycle
+        app.update();
     }
 }
@@ -0,0 +1,466 @@
+//! Plugin providing local two-player modes.
+//!
+//! In [PlayMode::HotSeat], both players share one graveyard and take turns, starting with player
+//! one.
+//! Willos can be given to a player with the "Player" LDtk enum field.
+//! Willos without it are moved by whoever's turn it is.
+//!
+//! In [PlayMode::Versus], the players race through mirrored copies of the same level, one after
+//! the other.
+//! Player one plays the level as it was designed, and player two plays its mirror image, so they
+//! can't simply repeat the moves they watched.
+//! Whoever solves it in fewer turns wins.
+//!
+//! Each player has their own `InputMap<GraveId>`.
+//! Player one uses the regular gravestone controls, player two uses
+//! `settings/gravestone_controls_player_two.json`.
+use crate::{
+    graveyard::{
+        gravestone::{GraveId, GraveLayout},
+        turn::TurnCount,
+        willo::WilloState,
+    },
+    ui::font_scale::{FontScale, FontSize},
+    AssetHolder, GameState,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_tilemap::tiles::{TileFlip, TilePos, TileStorage};
+use leafwing_input_manager::prelude::*;
+use std::{fs::File, io::BufReader};
+
+/// Plugin providing local two-player modes.
+pub struct MultiplayerPlugin;
+
+impl Plugin for MultiplayerPlugin {
+    fn build(&self, app: &mut App) {
+        let asset_folder = app.get_added_plugins::<AssetPlugin>()[0].file_path.clone();
+
+        app.init_resource::<PlayMode>()
+            .init_resource::<VersusRace>()
+            .insert_resource(PlayerTwoInputMap(
+                load_player_two_control_settings(asset_folder)
+                    .expect("unable to load player two gravestone control settings"),
+            ))
+            .add_systems(
+                OnExit(GameState::AssetLoading),
+                (spawn_player_controllers, spawn_turn_banner),
+            )
+            .add_systems(
+                Update,
+                (
+                    assign_willo_players,
+                    update_turn_banner.run_if(in_state(GameState::Graveyard)),
+                    mirror_versus_layers,
+                    unmirror_readables.run_if(not(in_state(GameState::AssetLoading))),
+                ),
+            );
+    }
+}
+
+/// Resource defining how many players are playing, and how.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Resource)]
+pub enum PlayMode {
+    /// A single player controls every willo.
+    #[default]
+    Solo,
+    /// Two players take turns in the same graveyard.
+    HotSeat,
+    /// Two players race through mirrored copies of the same level, one after the other.
+    Versus,
+}
+
+impl PlayMode {
+    /// Returns the name of this mode, as shown in menus.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayMode::Solo => "Solo",
+            PlayMode::HotSeat => "Hot-seat",
+            PlayMode::Versus => "Versus",
+        }
+    }
+}
+
+/// Component that marks which player an entity belongs to.
+///
+/// Willos may have it, and each player has a controller entity with it that holds their input.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub enum Player {
+    /// The first player, who always goes first.
+    #[default]
+    One,
+    /// The second player.
+    Two,
+}
+
+impl Player {
+    /// Returns the player who takes the turn after the given number of turns have been taken.
+    pub fn after_turns(turns: u32) -> Player {
+        if turns.is_multiple_of(2) {
+            Player::One
+        } else {
+            Player::Two
+        }
+    }
+
+    /// Returns the name of this player, as shown in the UI.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Player::One => "Player One",
+            Player::Two => "Player Two",
+        }
+    }
+}
+
+/// Resource tracking the progress of a [PlayMode::Versus] race on the current level.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Resource)]
+pub struct VersusRace {
+    player_one_turns: Option<u32>,
+}
+
+impl VersusRace {
+    /// Returns the player currently racing.
+    pub fn racer(&self) -> Player {
+        match self.player_one_turns {
+            None => Player::One,
+            Some(_) => Player::Two,
+        }
+    }
+}
+
+/// Component that marks level layers that are shown as a mirror image of themselves.
+///
+/// Player two races on mirrored levels in [PlayMode::Versus].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Mirrored;
+
+/// Component that marks entities that should still read the right way round in a [Mirrored]
+/// layer, like text and key icons.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Unmirrored;
+
+/// What should happen after a level is completed.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum LevelOutcome {
+    /// Move on to the next level, showing the given message on its level card, if any.
+    NextLevel(Option<String>),
+    /// Play the same level again, showing the given message on its level card.
+    Replay(String),
+}
+
+/// Decides what happens after a level has been completed in the given number of turns.
+pub fn complete_level(play_mode: PlayMode, race: &mut VersusRace, turns: u32) -> LevelOutcome {
+    match play_mode {
+        PlayMode::Solo => LevelOutcome::NextLevel(None),
+        PlayMode::HotSeat => {
+            let finisher = Player::after_turns(turns.saturating_sub(1));
+            LevelOutcome::NextLevel(Some(format!(
+                "{} finished the last level in {turns} turns",
+                finisher.name()
+            )))
+        }
+        PlayMode::Versus => match race.player_one_turns.take() {
+            None => {
+                race.player_one_turns = Some(turns);
+                LevelOutcome::Replay(format!(
+                    "{} took {turns} turns\n{}, your turn!",
+                    Player::One.name(),
+                    Player::Two.name()
+                ))
+            }
+            Some(player_one_turns) => {
+                let result = match player_one_turns.cmp(&turns) {
+                    std::cmp::Ordering::Less => {
+                        format!("{} wins, {player_one_turns} to {turns}", Player::One.name())
+                    }
+                    std::cmp::Ordering::Greater => {
+                        format!("{} wins, {turns} to {player_one_turns}", Player::Two.name())
+                    }
+                    std::cmp::Ordering::Equal => format!("A draw at {turns} turns"),
+                };
+                LevelOutcome::NextLevel(Some(result))
+            }
+        },
+    }
+}
+
+/// Resource holding player two's grave controls, loaded from settings.
+#[derive(Clone, Debug, Resource)]
+struct PlayerTwoInputMap(InputMap<GraveId>);
+
+fn load_player_two_control_settings(asset_folder: String) -> std::io::Result<InputMap<GraveId>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Ok(serde_json::from_reader(BufReader::new(File::open(
+            format!("{asset_folder}/../settings/gravestone_controls_player_two.json"),
+        )?))?)
+    }
+
+    // placed in a `#[cfg]` block rather than `if cfg!` so that changes to the file don't
+    // recompile non-wasm builds.
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(serde_json::from_str(include_str!(
+            "../../settings/gravestone_controls_player_two.json"
+        ))?)
+    }
+}
+
+/// System param for reading grave actions from whichever player is currently in control.
+#[derive(SystemParam)]
+pub struct GraveInput<'w, 's> {
+    play_mode: Res<'w, PlayMode>,
+    race: Res<'w, VersusRace>,
+    turn_counts: Query<'w, 's, &'static TurnCount>,
+    grave_layout: Res<'w, GraveLayout>,
+    solo_input: Res<'w, ActionState<GraveId>>,
+    player_inputs: Query<'w, 's, (&'static Player, &'static ActionState<GraveId>)>,
+}
+
+impl GraveInput<'_, '_> {
+    /// Returns the player in control of grave actions, or `None` in solo play.
+    pub fn active_player(&self) -> Option<Player> {
+        match *self.play_mode {
+            PlayMode::Solo => None,
+            PlayMode::HotSeat => Some(Player::after_turns(
+                self.turn_counts.iter().next().map_or(0, |turns| **turns),
+            )),
+            PlayMode::Versus => Some(self.race.racer()),
+        }
+    }
+
+    /// Returns the grave action that the player in control just pressed, if any.
+    pub fn just_pressed(&self) -> Option<GraveId> {
+        let action_state = match self.active_player() {
+            None => &*self.solo_input,
+            Some(active_player) => self
+                .player_inputs
+                .iter()
+                .find(|(player, _)| **player == active_player)
+                .map(|(_, action_state)| action_state)?,
+        };
+
+        self.grave_layout
+            .slots()
+            .map(|(grave_id, _)| grave_id)
+            .find(|grave_id| action_state.just_pressed(grave_id))
+    }
+
+    /// Returns whether the player in control moves a willo belonging to the given player.
+    pub fn controls(&self, willo_player: Option<&Player>) -> bool {
+        match (*self.play_mode, willo_player) {
+            (PlayMode::HotSeat, Some(willo_player)) => self.active_player() == Some(*willo_player),
+            _ => true,
+        }
+    }
+}
+
+fn spawn_player_controllers(
+    mut commands: Commands,
+    player_one_input_map: Res<InputMap<GraveId>>,
+    player_two_input_map: Res<PlayerTwoInputMap>,
+) {
+    commands.spawn((
+        Player::One,
+        player_one_input_map.clone(),
+        ActionState::<GraveId>::default(),
+    ));
+    commands.spawn((
+        Player::Two,
+        player_two_input_map.0.clone(),
+        ActionState::<GraveId>::default(),
+    ));
+}
+
+fn assign_willo_players(
+    mut commands: Commands,
+    willos: Query<(Entity, &EntityInstance), Added<WilloState>>,
+) {
+    for (entity, entity_instance) in willos.iter() {
+        let player = match entity_instance
+            .get_maybe_enum_field("Player")
+            .ok()
+            .and_then(|player| player.as_deref())
+        {
+            Some("One") => Player::One,
+            Some("Two") => Player::Two,
+            _ => continue,
+        };
+
+        commands.entity(entity).insert(player);
+    }
+}
+
+/// Mirrors the layers of levels horizontally while player two is racing.
+///
+/// Only visuals are mirrored, so the level plays exactly like the original in grid coordinates,
+/// while looking like its mirror image.
+/// Arrow blocks and movement tiles are flipped with their layer, and the control display mirrors
+/// the grave actions it shows, so every arrow points the way Willo moves on screen.
+/// Entity layers are flipped by their transform.
+/// Tile layers can't be, since tilemaps cull back faces, so their tiles are moved instead.
+fn mirror_versus_layers(
+    mut commands: Commands,
+    mut layers: Query<
+        (
+            Entity,
+            &LayerMetadata,
+            &mut Transform,
+            Option<&mut TileStorage>,
+        ),
+        Added<LayerMetadata>,
+    >,
+    mut tiles: Query<(&mut TilePos, &mut TileFlip)>,
+    play_mode: Res<PlayMode>,
+    race: Res<VersusRace>,
+) {
+    if *play_mode != PlayMode::Versus || race.racer() != Player::Two {
+        return;
+    }
+
+    for (entity, layer_metadata, mut transform, tile_storage) in layers.iter_mut() {
+        match tile_storage {
+            Some(mut tile_storage) => {
+                let width = tile_storage.size.x;
+                let tile_entities: Vec<Entity> = tile_storage.drain().collect();
+
+                for tile_entity in tile_entities {
+                    let Ok((mut tile_pos, mut tile_flip)) = tiles.get_mut(tile_entity) else {
+                        continue;
+                    };
+
+                    tile_pos.x = width - 1 - tile_pos.x;
+                    tile_flip.x = !tile_flip.x;
+                    tile_storage.set(&tile_pos, tile_entity);
+                }
+            }
+            None => {
+                // flipped around the level's left edge, then moved back into place
+                let level_width = (layer_metadata.c_wid * layer_metadata.grid_size) as f32;
+                transform.scale.x = -transform.scale.x.abs();
+                transform.translation.x = level_width - transform.translation.x;
+            }
+        }
+
+        commands.entity(entity).insert(Mirrored);
+    }
+}
+
+/// Flips [Unmirrored] entities back around if they're in a [Mirrored] layer.
+fn unmirror_readables(
+    mut readables: Query<(Entity, &mut Transform), With<Unmirrored>>,
+    parents: Query<&ChildOf>,
+    mirrored_layers: Query<(), With<Mirrored>>,
+) {
+    for (entity, mut transform) in readables.iter_mut() {
+        let mirrored = parents
+            .iter_ancestors(entity)
+            .any(|ancestor| mirrored_layers.contains(ancestor));
+
+        let scale_x = if mirrored {
+            -transform.scale.x.abs()
+        } else {
+            transform.scale.x.abs()
+        };
+
+        if transform.scale.x != scale_x {
+            transform.scale.x = scale_x;
+        }
+    }
+}
+
+/// Component that marks the UI text announcing whose turn it is.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+struct TurnBanner;
+
+fn spawn_turn_banner(mut commands: Commands, asset_holder: Res<AssetHolder>) {
+    commands.spawn((
+        Text::default(),
+        TextFont::from_font(asset_holder.font.clone()),
+        TextColor(Color::WHITE),
+        FontScale::from(FontSize::Small),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        },
+        TurnBanner,
+    ));
+}
+
+fn update_turn_banner(grave_input: GraveInput, mut banners: Query<&mut Text, With<TurnBanner>>) {
+    let text = match grave_input.active_player() {
+        Some(player) => format!("{}'s turn", player.name()),
+        None => String::new(),
+    };
+
+    for mut banner in banners.iter_mut() {
+        banner.set_if_neq(Text(text.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn versus_race_replays_then_picks_winner() {
+        let mut race = VersusRace::default();
+
+        assert_eq!(race.racer(), Player::One);
+        assert!(matches!(
+            complete_level(PlayMode::Versus, &mut race, 12),
+            LevelOutcome::Replay(_)
+        ));
+        assert_eq!(race.racer(), Player::Two);
+
+        assert_eq!(
+            complete_level(PlayMode::Versus, &mut race, 9),
+            LevelOutcome::NextLevel(Some("Player Two wins, 9 to 12".to_string()))
+        );
+        assert_eq!(race.racer(), Player::One);
+    }
+
+    #[test]
+    fn hot_seat_alternates_starting_with_player_one() {
+        assert_eq!(Player::after_turns(0), Player::One);
+        assert_eq!(Player::after_turns(1), Player::Two);
+        assert_eq!(Player::after_turns(2), Player::One);
+
+        assert_eq!(
+            complete_level(PlayMode::HotSeat, &mut VersusRace::default(), 3),
+            LevelOutcome::NextLevel(Some(
+                "Player One finished the last level in 3 turns".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn readables_are_flipped_back_in_mirrored_levels() {
+        let mut world = World::new();
+
+        let mirrored_layer = world
+            .spawn((Mirrored, Transform::from_scale(Vec3::new(-1., 1., 1.))))
+            .id();
+        let layer = world.spawn(Transform::default()).id();
+
+        let mirrored_gravestone = world.spawn(ChildOf(mirrored_layer)).id();
+        let mirrored_icon = world
+            .spawn((
+                Unmirrored,
+                Transform::default(),
+                ChildOf(mirrored_gravestone),
+            ))
+            .id();
+        let icon = world
+            .spawn((Unmirrored, Transform::default(), ChildOf(layer)))
+            .id();
+
+        world.run_system_once(unmirror_readables).unwrap();
+
+        assert_eq!(world.get::<Transform>(mirrored_icon).unwrap().scale.x, -1.);
+        assert_eq!(world.get::<Transform>(icon).unwrap().scale.x, 1.);
+    }
+}
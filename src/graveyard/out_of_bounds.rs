@@ -1,10 +1,18 @@
 //! Contains [`OutOfBoundsPlugin`], which places invisible walls on level boundaries.
+//!
+//! Levels with the "Wrap" LDtk level bool field set get no walls.
+//! Instead, their edges wrap around via [`WrapAround`].
 
-use crate::{sokoban::SokobanBlock, utils::any_match_filter, AssetHolder, GameState, UNIT_LENGTH};
+use crate::{
+    sokoban::{SokobanBlock, WrapAround},
+    utils::any_match_filter,
+    AssetHolder, GameState, UNIT_LENGTH,
+};
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 
-/// Plugin that spawns [`SokobanBlock::Static`] on the boundaries of LDtk levels.
+/// Plugin that spawns [`SokobanBlock::Static`] on the boundaries of LDtk levels, or makes their
+/// edges wrap around.
 pub struct OutOfBoundsPlugin;
 
 impl Plugin for OutOfBoundsPlugin {
@@ -24,6 +32,7 @@ fn spawn_level_boundaries(
     level_iids: Query<(Entity, &LevelIid), Added<LevelIid>>,
     ldtk_project_assets: Res<Assets<LdtkProject>>,
     asset_holder: Res<AssetHolder>,
+    mut wrap_around: ResMut<WrapAround>,
 ) {
     let ldtk_project = ldtk_project_assets
         .get(&asset_holder.ldtk)
@@ -37,6 +46,13 @@ fn spawn_level_boundaries(
 
             let level_grid_size = IVec2::new(level.px_wid, level.px_hei) / UNIT_LENGTH;
 
+            if let Ok(true) = level.get_bool_field("Wrap") {
+                *wrap_around = WrapAround(Some(level_grid_size));
+                return;
+            }
+
+            *wrap_around = WrapAround(None);
+
             commands
                 .entity(level_entity)
                 .with_children(|child_commands| {
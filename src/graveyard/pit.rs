@@ -0,0 +1,243 @@
+//! Plugin providing functionality for pit tiles.
+//!
+//! Pushing a gravestone into an open pit consumes the gravestone and fills the pit.
+//! Filled pits are walkable floor, but Willo will die if they walk into an open pit.
+use crate::{
+    graveyard::{
+        exorcism::{ExorcismEvent, ExorcismSets},
+        gravestone::GraveId,
+        volatile::{Sublimation, Volatile},
+        willo::WilloState,
+    },
+    history::{FlushHistoryCommands, History, HistoryPlugin},
+    sokoban::SokobanSets,
+    utils::any_match_filter,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_tilemap::tiles::TileVisible;
+
+/// Plugin providing functionality for pit tiles.
+///
+/// Pushing a gravestone into an open pit consumes the gravestone and fills the pit.
+/// Filled pits are walkable floor, but Willo will die if they walk into an open pit.
+pub struct PitPlugin;
+
+const PIT_INT_GRID_VALUE: i32 = 6;
+
+impl Plugin for PitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HistoryPlugin::<Pit, _>::run_in_state(GameState::Graveyard))
+            .add_systems(
+                Update,
+                (
+                    fill_pits
+                        .run_if(any_match_filter::<(With<GraveId>, Changed<GridCoords>)>)
+                        .after(SokobanSets::MoveResolution)
+                        .after(FlushHistoryCommands)
+                        .before(Sublimation),
+                    check_pit_death
+                        .in_set(ExorcismSets::CheckDeath)
+                        .after(fill_pits),
+                    visually_fill_pits,
+                )
+                    .run_if(in_state(GameState::Graveyard)),
+            )
+            .register_ldtk_int_cell::<PitBundle>(PIT_INT_GRID_VALUE);
+    }
+}
+
+/// Component defining whether a pit tile is still open or has been filled by a gravestone.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+pub enum Pit {
+    /// The pit is open, it will consume gravestones and kill Willo.
+    #[default]
+    Open,
+    /// The pit has been filled with a gravestone, and acts as floor.
+    Filled,
+    /// The pit is covered by cracked floor, which acts as floor until it crumbles.
+    ///
+    /// See [crate::graveyard::cracked].
+    Covered,
+}
+
+#[derive(Clone, Default, Bundle, LdtkIntCell)]
+struct PitBundle {
+    pit: Pit,
+    pit_history: History<Pit>,
+}
+
+/// Consumes solid gravestones that have moved into open pits.
+///
+/// Consumed gravestones are sublimated, which hides them and disables their grave action.
+fn fill_pits(
+    mut gravestones: Query<(&GridCoords, &mut Volatile), (With<GraveId>, Changed<GridCoords>)>,
+    mut pits: Query<(&GridCoords, &mut Pit)>,
+) {
+    for (gravestone_grid_coords, mut volatile) in gravestones.iter_mut() {
+        if !volatile.is_solid() {
+            continue;
+        }
+
+        if let Some((_, mut pit)) = pits.iter_mut().find(|(pit_grid_coords, pit)| {
+            *pit_grid_coords == gravestone_grid_coords && **pit == Pit::Open
+        }) {
+            *pit = Pit::Filled;
+            volatile.sublimate();
+        }
+    }
+}
+
+fn check_pit_death(
+    mut willo_query: Query<(Entity, &mut WilloState, &GridCoords), Changed<GridCoords>>,
+    pits: Query<(&GridCoords, &Pit)>,
+    mut death_event_writer: EventWriter<ExorcismEvent>,
+) {
+    for (willo_entity, mut willo, willo_grid_coords) in willo_query.iter_mut() {
+        let in_open_pit = pits.iter().any(|(pit_grid_coords, pit)| {
+            pit_grid_coords == willo_grid_coords && *pit == Pit::Open
+        });
+
+        if in_open_pit && *willo != WilloState::Dead {
+            *willo = WilloState::Dead;
+            death_event_writer.write(ExorcismEvent { willo_entity });
+        }
+    }
+}
+
+fn visually_fill_pits(mut pit_query: Query<(&mut TileVisible, &Pit), Changed<Pit>>) {
+    for (mut visibility, pit) in pit_query.iter_mut() {
+        // covered pits show the cracked floor tile
+        visibility.0 = *pit != Pit::Filled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        history::HistoryCommands,
+        sokoban::{Direction, SokobanBlock, SokobanCommand, SokobanPlugin},
+    };
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_event::<ExorcismEvent>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                PitPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    fn spawn_pit(app: &mut App, grid_coords: GridCoords, pit: Pit) -> Entity {
+        app.world_mut()
+            .spawn((grid_coords, pit, History::<Pit>::default()))
+            .id()
+    }
+
+    fn spawn_gravestone(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((
+                grid_coords,
+                GraveId('Q'),
+                Volatile::Solid,
+                SokobanBlock::Dynamic,
+            ))
+            .id()
+    }
+
+    fn spawn_willo(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((grid_coords, WilloState::Waiting, SokobanBlock::Dynamic))
+            .id()
+    }
+
+    fn move_block(app: &mut App, entity: Entity, direction: Direction) {
+        app.world_mut()
+            .send_event(SokobanCommand::Move { entity, direction });
+    }
+
+    #[test]
+    fn gravestone_fills_open_pit() {
+        let mut app = app_setup();
+        let pit = spawn_pit(&mut app, GridCoords::new(1, 0), Pit::Open);
+        let gravestone = spawn_gravestone(&mut app, GridCoords::new(0, 0));
+
+        move_block(&mut app, gravestone, Direction::Right);
+        app.update();
+
+        assert_eq!(app.world().get::<Pit>(pit), Some(&Pit::Filled));
+        assert_eq!(
+            app.world().get::<Volatile>(gravestone),
+            Some(&Volatile::Sublimated)
+        );
+    }
+
+    #[test]
+    fn willo_dies_in_open_pit() {
+        let mut app = app_setup();
+        spawn_pit(&mut app, GridCoords::new(1, 0), Pit::Open);
+        let willo = spawn_willo(&mut app, GridCoords::new(0, 0));
+
+        move_block(&mut app, willo, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            app.world().get::<WilloState>(willo),
+            Some(&WilloState::Dead)
+        );
+        assert_eq!(app.world().resource::<Events<ExorcismEvent>>().len(), 1);
+    }
+
+    #[test]
+    fn willo_survives_filled_and_covered_pits() {
+        let mut app = app_setup();
+        spawn_pit(&mut app, GridCoords::new(1, 0), Pit::Filled);
+        spawn_pit(&mut app, GridCoords::new(2, 0), Pit::Covered);
+        let willo = spawn_willo(&mut app, GridCoords::new(0, 0));
+
+        move_block(&mut app, willo, Direction::Right);
+        app.update();
+        move_block(&mut app, willo, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(willo),
+            Some(&GridCoords::new(2, 0))
+        );
+        assert_eq!(
+            app.world().get::<WilloState>(willo),
+            Some(&WilloState::Waiting)
+        );
+    }
+
+    #[test]
+    fn rewinding_reopens_filled_pit() {
+        let mut app = app_setup();
+        let pit = spawn_pit(&mut app, GridCoords::new(1, 0), Pit::Open);
+        let gravestone = spawn_gravestone(&mut app, GridCoords::new(0, 0));
+
+        app.world_mut().send_event(HistoryCommands::Record);
+        app.update();
+
+        move_block(&mut app, gravestone, Direction::Right);
+        app.update();
+        assert_eq!(app.world().get::<Pit>(pit), Some(&Pit::Filled));
+
+        app.world_mut().send_event(HistoryCommands::Rewind);
+        app.update();
+        assert_eq!(app.world().get::<Pit>(pit), Some(&Pit::Open));
+    }
+}
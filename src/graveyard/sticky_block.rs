@@ -0,0 +1,39 @@
+//! Plugin providing functionality for sticky blocks.
+//!
+//! Sticky blocks are sokoban blocks that bond to other sticky blocks when they touch.
+//! Afterwards, pushing any one of them moves the whole bonded group.
+use crate::{
+    history::{History, HistoryPlugin},
+    sokoban::{Bonds, SokobanBlock},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+/// Plugin providing functionality for sticky blocks.
+///
+/// Sticky blocks are sokoban blocks that bond to other sticky blocks when they touch.
+/// Afterwards, pushing any one of them moves the whole bonded group.
+pub struct StickyBlockPlugin;
+
+impl Plugin for StickyBlockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HistoryPlugin::<Bonds, _>::run_in_state(
+            GameState::Graveyard,
+        ))
+        .register_ldtk_entity::<StickyBlockBundle>("StickyBlock");
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct StickyBlockBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    history: History<GridCoords>,
+    #[with(SokobanBlock::new_sticky)]
+    sokoban_block: SokobanBlock,
+    bonds: Bonds,
+    bonds_history: History<Bonds>,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
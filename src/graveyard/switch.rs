@@ -0,0 +1,472 @@
+//! Plugin providing functionality for floor switches and the entities they control.
+//!
+//! Switches are linked to their targets in LDtk via the "Targets" entity reference array field.
+//! - "PressurePlate" switches are active while any sokoban block or Willo stands on them.
+//! - "ToggleSwitch" switches flip between active and inactive whenever something steps on them.
+//!
+//! Targets are [Switchable] entities, like switch walls and arrow blocks, or rotating arrow blocks,
+//! which turn whenever a switch targeting them activates.
+//! A target is flipped from its initial state while any of its linked switches are active.
+//! Switch walls are only solid while they're on and nothing stands in them, so a wall that switches
+//! on while occupied stays open until the tile is left.
+use crate::{
+    history::{FlushHistoryCommands, History, HistoryPlugin},
+    sokoban::{SokobanBlock, SokobanSets},
+    utils::any_match_filter,
+    GameState,
+};
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_ecs_ldtk::prelude::*;
+
+/// Sets used by switch systems.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum SwitchSets {
+    /// Set for the systems that update switches and flip their targets.
+    UpdateSwitches,
+}
+
+/// Plugin providing functionality for floor switches and the entities they control.
+pub struct SwitchPlugin;
+
+impl Plugin for SwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SwitchEvent>()
+            .add_plugins(HistoryPlugin::<Switch, _>::run_in_state(
+                GameState::Graveyard,
+            ))
+            .configure_sets(
+                Update,
+                SwitchSets::UpdateSwitches
+                    .run_if(in_state(GameState::Graveyard))
+                    .after(SokobanSets::MoveResolution)
+                    .after(FlushHistoryCommands),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_switches,
+                    (switch_events, flip_switchables).run_if(any_match_filter::<Changed<Switch>>),
+                    toggle_switch_walls.run_if(
+                        any_match_filter::<Changed<Switchable>>
+                            .or(any_match_filter::<(With<SokobanBlock>, Changed<GridCoords>)>),
+                    ),
+                )
+                    .chain()
+                    .in_set(SwitchSets::UpdateSwitches),
+            )
+            .register_ldtk_entity::<SwitchBundle>("PressurePlate")
+            .register_ldtk_entity::<SwitchBundle>("ToggleSwitch")
+            .register_ldtk_entity::<SwitchWallBundle>("SwitchWall");
+    }
+}
+
+/// Enumerates the kinds of switches.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SwitchKind {
+    /// Active while occupied.
+    #[default]
+    PressurePlate,
+    /// Flips whenever it becomes occupied.
+    Toggle,
+}
+
+/// Component storing the state of a switch.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+pub struct Switch {
+    kind: SwitchKind,
+    active: bool,
+    occupied: bool,
+}
+
+impl Switch {
+    /// Returns `true` if the switch is currently active.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+impl From<&EntityInstance> for Switch {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let kind = if entity_instance.identifier.contains("Toggle") {
+            SwitchKind::Toggle
+        } else {
+            SwitchKind::PressurePlate
+        };
+
+        Switch { kind, ..default() }
+    }
+}
+
+/// Component storing the iids of the [Switchable] entities a switch controls.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Component)]
+pub struct SwitchTargets(Vec<String>);
+
+impl From<&EntityInstance> for SwitchTargets {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        SwitchTargets(
+            entity_instance
+                .get_maybe_entity_refs_field("Targets")
+                .expect("switches should have a list of targets")
+                .iter()
+                .flatten()
+                .map(|entity_ref| entity_ref.entity_iid.clone())
+                .collect(),
+        )
+    }
+}
+
+impl SwitchTargets {
+    /// Returns `true` if the entity with the given iid is one of these targets.
+    pub fn contains(&self, iid: &EntityIid) -> bool {
+        self.0.iter().any(|target| *target == iid.as_str())
+    }
+}
+
+/// Event that fires when a switch's state changes.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Event)]
+pub enum SwitchEvent {
+    /// The switch became active.
+    Activated {
+        /// The switch that became active.
+        switch_entity: Entity,
+    },
+    /// The switch became inactive.
+    Deactivated {
+        /// The switch that became inactive.
+        switch_entity: Entity,
+    },
+}
+
+/// Component for entities that can be flipped on and off by switches.
+///
+/// The state is derived from the linked switches, so it needs no history of its own.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Component)]
+pub struct Switchable {
+    initially_on: bool,
+    on: bool,
+}
+
+impl Default for Switchable {
+    fn default() -> Self {
+        Switchable::new(true)
+    }
+}
+
+impl Switchable {
+    /// Construct a new [Switchable] in the given initial state.
+    pub fn new(initially_on: bool) -> Self {
+        Switchable {
+            initially_on,
+            on: initially_on,
+        }
+    }
+
+    /// Returns `true` if the entity is currently switched on.
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct SwitchBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[from_entity_instance]
+    switch: Switch,
+    switch_history: History<Switch>,
+    #[from_entity_instance]
+    targets: SwitchTargets,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+/// Component that marks static walls that can be switched on and off.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+struct SwitchWall;
+
+fn switch_wall_switchable(entity_instance: &EntityInstance) -> Switchable {
+    Switchable::new(
+        *entity_instance
+            .get_bool_field("On")
+            .expect("switch walls should have an initial state"),
+    )
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct SwitchWallBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[with(SokobanBlock::new_static)]
+    sokoban_block: SokobanBlock,
+    switch_wall: SwitchWall,
+    #[with(switch_wall_switchable)]
+    switchable: Switchable,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+fn update_switches(
+    mut switches: Query<(&GridCoords, &mut Switch)>,
+    blocks: Query<&GridCoords, With<SokobanBlock>>,
+) {
+    for (switch_grid_coords, mut switch) in switches.iter_mut() {
+        let occupied = blocks
+            .iter()
+            .any(|block_grid_coords| block_grid_coords == switch_grid_coords);
+
+        let active = match switch.kind {
+            SwitchKind::PressurePlate => occupied,
+            SwitchKind::Toggle if occupied && !switch.occupied => !switch.active,
+            SwitchKind::Toggle => switch.active,
+        };
+
+        let kind = switch.kind;
+        switch.set_if_neq(Switch {
+            kind,
+            active,
+            occupied,
+        });
+    }
+}
+
+/// Fires [SwitchEvent]s for every change in switch state, including changes from rewinding.
+fn switch_events(
+    switches: Query<(Entity, &Switch), Changed<Switch>>,
+    mut switch_events: EventWriter<SwitchEvent>,
+    mut last_active: Local<HashMap<Entity, bool>>,
+) {
+    for (switch_entity, switch) in switches.iter() {
+        let was_active = last_active.insert(switch_entity, switch.active);
+
+        match (was_active.unwrap_or_default(), switch.active) {
+            (false, true) => {
+                switch_events.write(SwitchEvent::Activated { switch_entity });
+            }
+            (true, false) => {
+                switch_events.write(SwitchEvent::Deactivated { switch_entity });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn flip_switchables(
+    switches: Query<(&Switch, &SwitchTargets)>,
+    mut switchables: Query<(&EntityIid, &mut Switchable)>,
+) {
+    for (iid, mut switchable) in switchables.iter_mut() {
+        let flipped = switches
+            .iter()
+            .any(|(switch, targets)| switch.active && targets.contains(iid));
+
+        let initially_on = switchable.initially_on;
+        switchable.set_if_neq(Switchable {
+            initially_on,
+            on: initially_on != flipped,
+        });
+    }
+}
+
+fn toggle_switch_walls(
+    mut commands: Commands,
+    mut switch_walls: Query<
+        (
+            Entity,
+            &GridCoords,
+            &Switchable,
+            &mut Visibility,
+            Has<SokobanBlock>,
+        ),
+        With<SwitchWall>,
+    >,
+    blocks: Query<&GridCoords, (With<SokobanBlock>, Without<SwitchWall>)>,
+) {
+    for (entity, wall_grid_coords, switchable, mut visibility, solid) in switch_walls.iter_mut() {
+        let occupied = blocks
+            .iter()
+            .any(|block_grid_coords| block_grid_coords == wall_grid_coords);
+
+        // derived from occupancy rather than remembered, so it stays in sync when rewound
+        let should_be_solid = switchable.is_on() && !occupied;
+
+        if should_be_solid && !solid {
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).insert(SokobanBlock::Static);
+        } else if !should_be_solid && solid {
+            *visibility = Visibility::Hidden;
+            commands.entity(entity).remove::<SokobanBlock>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        history::HistoryCommands,
+        sokoban::{Direction, SokobanCommand, SokobanPlugin},
+    };
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                HistoryPlugin::<GridCoords, _>::run_in_state(GameState::Graveyard),
+                SwitchPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    /// Spawns a switch of the given kind at (1, 0), targeting a switch wall at (0, 3).
+    fn spawn_switch(app: &mut App, kind: SwitchKind, wall_on: bool) -> (Entity, Entity) {
+        let switch = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(1, 0),
+                Switch { kind, ..default() },
+                History::<Switch>::default(),
+                SwitchTargets(vec!["wall".to_string()]),
+            ))
+            .id();
+
+        let wall = app
+            .world_mut()
+            .spawn((
+                EntityIid::new("wall"),
+                GridCoords::new(0, 3),
+                SwitchWall,
+                Switchable::new(wall_on),
+                Visibility::Inherited,
+            ))
+            .id();
+
+        (switch, wall)
+    }
+
+    fn spawn_block(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((
+                grid_coords,
+                History::<GridCoords>::default(),
+                SokobanBlock::Dynamic,
+            ))
+            .id()
+    }
+
+    fn move_block(app: &mut App, entity: Entity, direction: Direction) {
+        app.world_mut().send_event(HistoryCommands::Record);
+        app.world_mut()
+            .send_event(SokobanCommand::Move { entity, direction });
+        app.update();
+    }
+
+    fn is_active(app: &App, switch: Entity) -> bool {
+        app.world().get::<Switch>(switch).unwrap().is_active()
+    }
+
+    #[test]
+    fn pressure_plate_is_active_while_occupied() {
+        let mut app = app_setup();
+        let (switch, _) = spawn_switch(&mut app, SwitchKind::PressurePlate, true);
+        let block = spawn_block(&mut app, GridCoords::new(0, 0));
+        app.update();
+        assert!(!is_active(&app, switch));
+
+        move_block(&mut app, block, Direction::Right);
+        assert!(is_active(&app, switch));
+
+        move_block(&mut app, block, Direction::Right);
+        assert!(!is_active(&app, switch));
+    }
+
+    #[test]
+    fn toggle_switch_flips_each_time_its_stepped_on() {
+        let mut app = app_setup();
+        let (switch, _) = spawn_switch(&mut app, SwitchKind::Toggle, true);
+        let block = spawn_block(&mut app, GridCoords::new(0, 0));
+        app.update();
+
+        move_block(&mut app, block, Direction::Right);
+        assert!(is_active(&app, switch));
+
+        move_block(&mut app, block, Direction::Right);
+        assert!(is_active(&app, switch));
+
+        move_block(&mut app, block, Direction::Left);
+        assert!(!is_active(&app, switch));
+    }
+
+    #[test]
+    fn switch_wall_follows_its_switch() {
+        let mut app = app_setup();
+        let (_, wall) = spawn_switch(&mut app, SwitchKind::PressurePlate, false);
+        let block = spawn_block(&mut app, GridCoords::new(0, 0));
+        app.update();
+        assert!(app.world().get::<SokobanBlock>(wall).is_none());
+
+        move_block(&mut app, block, Direction::Right);
+        assert_eq!(
+            app.world().get::<SokobanBlock>(wall),
+            Some(&SokobanBlock::Static)
+        );
+        assert_eq!(
+            app.world().get::<Visibility>(wall),
+            Some(&Visibility::Inherited)
+        );
+    }
+
+    #[test]
+    fn switch_wall_stays_open_while_occupied() {
+        let mut app = app_setup();
+        let (_, wall) = spawn_switch(&mut app, SwitchKind::Toggle, false);
+        let block = spawn_block(&mut app, GridCoords::new(0, 0));
+        let willo = spawn_block(&mut app, GridCoords::new(0, 3));
+        app.update();
+
+        move_block(&mut app, block, Direction::Right);
+        assert!(app.world().get::<SokobanBlock>(wall).is_none());
+
+        move_block(&mut app, willo, Direction::Up);
+        assert_eq!(
+            app.world().get::<SokobanBlock>(wall),
+            Some(&SokobanBlock::Static)
+        );
+    }
+
+    #[test]
+    fn rewinding_into_a_switched_on_wall_keeps_it_open() {
+        let mut app = app_setup();
+        let (switch, wall) = spawn_switch(&mut app, SwitchKind::Toggle, false);
+        let block = spawn_block(&mut app, GridCoords::new(0, 0));
+        let willo = spawn_block(&mut app, GridCoords::new(0, 3));
+        app.update();
+
+        move_block(&mut app, block, Direction::Right);
+        move_block(&mut app, willo, Direction::Up);
+        assert!(app.world().get::<SokobanBlock>(wall).is_some());
+
+        app.world_mut().send_event(HistoryCommands::Rewind);
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(willo),
+            Some(&GridCoords::new(0, 3))
+        );
+        assert!(is_active(&app, switch));
+        assert!(app.world().get::<SokobanBlock>(wall).is_none());
+        assert_eq!(
+            app.world().get::<Visibility>(wall),
+            Some(&Visibility::Hidden)
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Plugin providing functionality for teleporters.
+//!
+//! Teleporters come in pairs, linked in LDtk via the "Partner" entity reference field.
+//! Any sokoban entity that finishes a move on a teleporter is warped to its partner, unless the
+//! partner is occupied.
+//! Teleporters without a partner do nothing.
+use crate::{
+    sokoban::{Bonds, Direction, MoveEvent, SokobanBlock, SokobanSets, Warp},
+    GameState,
+};
+use bevy::{platform::collections::HashSet, prelude::*};
+use bevy_ecs_ldtk::prelude::*;
+
+/// Plugin providing functionality for teleporters.
+///
+/// Teleporters come in pairs, linked in LDtk via the "Partner" entity reference field.
+/// Any sokoban entity that finishes a move on a teleporter is warped to its partner, unless the
+/// partner is occupied.
+pub struct TeleporterPlugin;
+
+impl Plugin for TeleporterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            teleport
+                .run_if(in_state(GameState::Graveyard))
+                .run_if(on_event::<MoveEvent<Direction>>)
+                .in_set(SokobanSets::MoveResolution),
+        )
+        .register_ldtk_entity::<TeleporterBundle>("Teleporter");
+    }
+}
+
+/// Component that marks teleporters and stores the iid of their partner, if they have one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Component)]
+pub struct Teleporter {
+    partner: Option<String>,
+}
+
+impl From<&EntityInstance> for Teleporter {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let partner = entity_instance
+            .get_entity_ref_field("Partner")
+            .ok()
+            .map(|partner| partner.entity_iid.clone());
+
+        if partner.is_none() {
+            warn!("teleporter {} has no partner", entity_instance.iid);
+        }
+
+        Teleporter { partner }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct TeleporterBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[from_entity_instance]
+    teleporter: Teleporter,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+/// Warps entities that just moved onto a teleporter to its partner.
+///
+/// Only entities that moved via the sokoban plugin are teleported, so restoring positions from
+/// history never triggers a teleport.
+/// Bonded sticky blocks are never teleported, since that would tear their group apart.
+fn teleport(
+    mut commands: Commands,
+    mut move_events: EventReader<MoveEvent<Direction>>,
+    teleporters: Query<(&EntityIid, &GridCoords, &Teleporter)>,
+    mut sokoban_entities: Query<
+        (&mut GridCoords, Option<&Bonds>),
+        (With<SokobanBlock>, Without<Teleporter>),
+    >,
+) {
+    let moved_entities: HashSet<Entity> = move_events.read().map(|event| event.entity).collect();
+
+    for entity in moved_entities {
+        let Ok((grid_coords, bonds)) = sokoban_entities.get(entity) else {
+            continue;
+        };
+
+        if bonds.is_some_and(|bonds| !bonds.is_empty()) {
+            continue;
+        }
+
+        let Some((_, _, teleporter)) = teleporters
+            .iter()
+            .find(|(_, teleporter_grid_coords, _)| *teleporter_grid_coords == grid_coords)
+        else {
+            continue;
+        };
+
+        let Some(partner_iid) = teleporter.partner.clone().map(EntityIid::new) else {
+            continue;
+        };
+
+        let Some((_, &partner_grid_coords, _)) = teleporters
+            .iter()
+            .find(|(entity_iid, ..)| **entity_iid == partner_iid)
+        else {
+            continue;
+        };
+
+        let partner_occupied = sokoban_entities
+            .iter()
+            .any(|(other_grid_coords, _)| *other_grid_coords == partner_grid_coords);
+
+        if !partner_occupied {
+            if let Ok((mut grid_coords, _)) = sokoban_entities.get_mut(entity) {
+                *grid_coords = partner_grid_coords;
+                commands.entity(entity).insert(Warp);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sokoban::{SokobanCommand, SokobanPlugin};
+    use bevy::state::app::StatesPlugin;
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                TeleporterPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        // a pair of teleporters at (1, 0) and (5, 0)
+        app.world_mut().spawn((
+            EntityIid::new("a"),
+            GridCoords::new(1, 0),
+            Teleporter {
+                partner: Some("b".to_string()),
+            },
+        ));
+        app.world_mut().spawn((
+            EntityIid::new("b"),
+            GridCoords::new(5, 0),
+            Teleporter {
+                partner: Some("a".to_string()),
+            },
+        ));
+
+        app
+    }
+
+    fn spawn_block(app: &mut App, grid_coords: GridCoords) -> Entity {
+        app.world_mut()
+            .spawn((grid_coords, SokobanBlock::Dynamic))
+            .id()
+    }
+
+    fn move_block(app: &mut App, entity: Entity, direction: Direction) {
+        app.world_mut()
+            .send_event(SokobanCommand::Move { entity, direction });
+    }
+
+    #[test]
+    fn block_moving_onto_teleporter_warps_to_partner() {
+        let mut app = app_setup();
+        let block = spawn_block(&mut app, GridCoords::new(0, 0));
+
+        move_block(&mut app, block, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(5, 0))
+        );
+        assert!(app.world().get::<Warp>(block).is_some());
+    }
+
+    #[test]
+    fn occupied_partner_blocks_teleport() {
+        let mut app = app_setup();
+        let block = spawn_block(&mut app, GridCoords::new(0, 0));
+        spawn_block(&mut app, GridCoords::new(5, 0));
+
+        move_block(&mut app, block, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(1, 0))
+        );
+        assert!(app.world().get::<Warp>(block).is_none());
+    }
+
+    #[test]
+    fn bonded_blocks_arent_teleported() {
+        let mut app = app_setup();
+        let block = app
+            .world_mut()
+            .spawn((GridCoords::new(0, 0), SokobanBlock::Sticky))
+            .id();
+        let other = app
+            .world_mut()
+            .spawn((GridCoords::new(0, 1), SokobanBlock::Sticky))
+            .id();
+
+        let mut bonds = Bonds::default();
+        bonds.insert(other);
+        app.world_mut().entity_mut(block).insert(bonds);
+
+        let mut bonds = Bonds::default();
+        bonds.insert(block);
+        app.world_mut().entity_mut(other).insert(bonds);
+
+        move_block(&mut app, block, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(1, 0))
+        );
+        assert!(app.world().get::<Warp>(block).is_none());
+    }
+
+    #[test]
+    fn unpaired_teleporters_are_skipped() {
+        let mut app = app_setup();
+        app.world_mut().spawn((
+            EntityIid::new("c"),
+            GridCoords::new(1, 3),
+            Teleporter { partner: None },
+        ));
+        let block = spawn_block(&mut app, GridCoords::new(0, 3));
+
+        move_block(&mut app, block, Direction::Right);
+        app.update();
+
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(1, 3))
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! Plugin providing functionality for timed volatile tiles.
+//!
+//! Timed volatiles change their [Volatile] state by themselves after a number of turns, defined in
+//! LDtk by the "Turns" int field, which must be at least 1.
+//! - "SublimatingExorcism" tiles start solid, and sublimate once their countdown is over.
+//! - "ResolidifyingExorcism" tiles start sublimated, and resolidify once their countdown is over.
+//!   Any solid volatile on the tile at that moment, like Willo, is sublimated along with it.
+//!
+//! Their material is defined by the optional "Material" string field, and defaults to "Exorcism".
+use crate::{
+    graveyard::{
+        carry::Carried,
+        multiplayer::Unmirrored,
+        turn::TurnSets,
+        volatile::{Volatile, VolatileMaterial},
+    },
+    history::{History, HistoryPlugin},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+/// Plugin providing functionality for timed volatile tiles.
+pub struct TimedVolatilePlugin;
+
+impl Plugin for TimedVolatilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HistoryPlugin::<TimedVolatile, _>::run_in_state(
+            GameState::Graveyard,
+        ))
+        .add_systems(
+            Update,
+            (
+                count_down_timed_volatiles.in_set(TurnSets::ActorStep),
+                spawn_countdown_text,
+                update_countdown_text,
+            )
+                .run_if(in_state(GameState::Graveyard)),
+        )
+        .register_ldtk_entity::<TimedVolatileBundle>("SublimatingExorcism")
+        .register_ldtk_entity::<TimedVolatileBundle>("ResolidifyingExorcism");
+    }
+}
+
+/// Component defining the countdown of a timed volatile.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+pub enum TimedVolatile {
+    /// The entity is solid, and will sublimate after the remaining turns.
+    Sublimating(u32),
+    /// The entity is sublimated, and will resolidify after the remaining turns.
+    Resolidifying(u32),
+    /// The countdown is over, or was interrupted by a collision.
+    #[default]
+    Expired,
+}
+
+impl From<&EntityInstance> for TimedVolatile {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let turns = *entity_instance
+            .get_int_field("Turns")
+            .expect("timed volatiles should have a number of turns");
+
+        // a countdown below 1 would never have a turn to count down
+        if turns < 1 {
+            panic!("encountered bad timed volatile turns: {turns}");
+        }
+
+        let turns = turns as u32;
+
+        if entity_instance.identifier.contains("Resolidifying") {
+            TimedVolatile::Resolidifying(turns)
+        } else {
+            TimedVolatile::Sublimating(turns)
+        }
+    }
+}
+
+fn initial_volatile(entity_instance: &EntityInstance) -> Volatile {
+    match TimedVolatile::from(entity_instance) {
+        TimedVolatile::Resolidifying(_) => Volatile::Sublimated,
+        _ => Volatile::Solid,
+    }
+}
+
+fn timed_volatile_material(entity_instance: &EntityInstance) -> VolatileMaterial {
+    match entity_instance.get_maybe_string_field("Material") {
+        Ok(Some(material)) => VolatileMaterial::new(material),
+        _ => VolatileMaterial::new("Exorcism"),
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+struct TimedVolatileBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[with(initial_volatile)]
+    volatile: Volatile,
+    volatile_history: History<Volatile>,
+    #[with(timed_volatile_material)]
+    material: VolatileMaterial,
+    material_history: History<VolatileMaterial>,
+    #[from_entity_instance]
+    timed_volatile: TimedVolatile,
+    timed_volatile_history: History<TimedVolatile>,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+/// Component that marks the text displaying a timed volatile's remaining turns.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+struct CountdownText;
+
+fn count_down_timed_volatiles(
+    mut timed_volatiles: Query<(Entity, &GridCoords, &mut TimedVolatile)>,
+    mut volatiles: Query<(Entity, &GridCoords, &mut Volatile), Without<Carried>>,
+) {
+    for (entity, grid_coords, mut timed_volatile) in timed_volatiles.iter_mut() {
+        let Ok((.., volatile)) = volatiles.get(entity) else {
+            continue;
+        };
+
+        // Sublimated early by a collision, so the countdown no longer matters.
+        if matches!(*timed_volatile, TimedVolatile::Sublimating(_)) && !volatile.is_solid() {
+            *timed_volatile = TimedVolatile::Expired;
+            continue;
+        }
+
+        let countdown = *timed_volatile;
+        if !timed_volatile.tick() {
+            continue;
+        }
+
+        match countdown {
+            TimedVolatile::Sublimating(_) => {
+                if let Ok((.., mut volatile)) = volatiles.get_mut(entity) {
+                    volatile.sublimate();
+                }
+            }
+            TimedVolatile::Resolidifying(_) => {
+                let mut occupied = false;
+                for (other_entity, other_grid_coords, mut other_volatile) in volatiles.iter_mut() {
+                    if other_entity != entity
+                        && other_grid_coords == grid_coords
+                        && other_volatile.is_solid()
+                    {
+                        other_volatile.sublimate();
+                        occupied = true;
+                    }
+                }
+
+                // Resolidifying onto a solid sublimates both, as if they had collided.
+                if !occupied {
+                    if let Ok((.., mut volatile)) = volatiles.get_mut(entity) {
+                        volatile.resolidify();
+                    }
+                }
+            }
+            TimedVolatile::Expired => {}
+        }
+    }
+}
+
+impl TimedVolatile {
+    /// Counts down one turn, returning `true` if the countdown just finished.
+    fn tick(&mut self) -> bool {
+        match self {
+            TimedVolatile::Sublimating(remaining) | TimedVolatile::Resolidifying(remaining)
+                if *remaining > 1 =>
+            {
+                *remaining -= 1;
+                false
+            }
+            TimedVolatile::Sublimating(_) | TimedVolatile::Resolidifying(_) => {
+                *self = TimedVolatile::Expired;
+                true
+            }
+            TimedVolatile::Expired => false,
+        }
+    }
+
+    fn remaining(&self) -> Option<u32> {
+        match self {
+            TimedVolatile::Sublimating(remaining) | TimedVolatile::Resolidifying(remaining) => {
+                Some(*remaining)
+            }
+            TimedVolatile::Expired => None,
+        }
+    }
+}
+
+fn spawn_countdown_text(
+    mut commands: Commands,
+    timed_volatiles: Query<Entity, Added<TimedVolatile>>,
+    assets: Res<AssetServer>,
+) {
+    for entity in timed_volatiles.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                CountdownText,
+                Text2d::default(),
+                TextFont::from_font(assets.load("fonts/WayfarersToyBoxRegular-gxxER.ttf"))
+                    .with_font_size(24.),
+                TextColor(Color::WHITE),
+                Transform::from_xyz(0., 0., 0.1),
+                Unmirrored,
+            ));
+        });
+    }
+}
+
+fn update_countdown_text(
+    mut timed_volatiles: Query<
+        (&TimedVolatile, &Volatile, &Children, &mut Sprite),
+        Or<(Changed<TimedVolatile>, Changed<Volatile>, Added<Children>)>,
+    >,
+    mut countdown_texts: Query<&mut Text2d, With<CountdownText>>,
+) {
+    for (timed_volatile, volatile, children, mut sprite) in timed_volatiles.iter_mut() {
+        sprite.color = sprite
+            .color
+            .with_alpha(if volatile.is_solid() { 1. } else { 0.3 });
+
+        for child in children.iter() {
+            if let Ok(mut text) = countdown_texts.get_mut(child) {
+                text.0 = timed_volatile
+                    .remaining()
+                    .map(|remaining| remaining.to_string())
+                    .unwrap_or_default();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy_ecs_ldtk::ldtk::{FieldInstance, FieldValue};
+
+    fn timed_volatile_instance(identifier: &str, turns: i32) -> EntityInstance {
+        EntityInstance {
+            identifier: identifier.to_string(),
+            field_instances: vec![FieldInstance {
+                identifier: "Turns".to_string(),
+                tile: None,
+                field_instance_type: String::new(),
+                value: FieldValue::Int(Some(turns)),
+                def_uid: 0,
+                real_editor_values: Vec::new(),
+            }],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn countdown_starts_at_its_turns() {
+        assert_eq!(
+            TimedVolatile::from(&timed_volatile_instance("SublimatingExorcism", 3)),
+            TimedVolatile::Sublimating(3)
+        );
+        assert_eq!(
+            TimedVolatile::from(&timed_volatile_instance("ResolidifyingExorcism", 1)),
+            TimedVolatile::Resolidifying(1)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bad timed volatile turns")]
+    fn negative_turns_panic() {
+        let _ = TimedVolatile::from(&timed_volatile_instance("SublimatingExorcism", -1));
+    }
+
+    #[test]
+    fn countdown_finishes_after_its_turns() {
+        let mut timed_volatile = TimedVolatile::Resolidifying(2);
+
+        assert!(!timed_volatile.tick());
+        assert_eq!(timed_volatile, TimedVolatile::Resolidifying(1));
+        assert!(timed_volatile.tick());
+        assert_eq!(timed_volatile, TimedVolatile::Expired);
+        assert!(!timed_volatile.tick());
+    }
+
+    #[test]
+    fn resolidifying_while_occupied_sublimates_the_occupant() {
+        let mut world = World::new();
+
+        let tile = world
+            .spawn((
+                GridCoords::new(0, 0),
+                Volatile::Sublimated,
+                TimedVolatile::Resolidifying(1),
+            ))
+            .id();
+        let occupant = world.spawn((GridCoords::new(0, 0), Volatile::Solid)).id();
+        let neighbor = world.spawn((GridCoords::new(1, 0), Volatile::Solid)).id();
+
+        world.run_system_once(count_down_timed_volatiles).unwrap();
+
+        assert_eq!(
+            *world.get::<TimedVolatile>(tile).unwrap(),
+            TimedVolatile::Expired
+        );
+        assert_eq!(*world.get::<Volatile>(tile).unwrap(), Volatile::Sublimated);
+        assert_eq!(
+            *world.get::<Volatile>(occupant).unwrap(),
+            Volatile::Sublimated
+        );
+        assert_eq!(*world.get::<Volatile>(neighbor).unwrap(), Volatile::Solid);
+    }
+
+    #[test]
+    fn resolidifying_while_unoccupied_becomes_solid() {
+        let mut world = World::new();
+
+        let tile = world
+            .spawn((
+                GridCoords::new(0, 0),
+                Volatile::Sublimated,
+                TimedVolatile::Resolidifying(1),
+            ))
+            .id();
+        world.spawn((GridCoords::new(1, 0), Volatile::Solid));
+
+        world.run_system_once(count_down_timed_volatiles).unwrap();
+
+        assert_eq!(*world.get::<Volatile>(tile).unwrap(), Volatile::Solid);
+    }
+}
@@ -0,0 +1,87 @@
+//! Plugin providing a turn structure for the graveyard.
+//!
+//! A turn ends once the final move of Willo's grave action has been resolved.
+//! Afterwards, other actors in the graveyard can take a step of their own in [TurnSets::ActorStep],
+//! seeing Willo where the grave action left them.
+//!
+//! Actors should track their own state with a [HistoryPlugin] so that their steps can be rewound
+//! along with Willo's.
+//! Actors may move via sokoban commands, which are resolved on their own, after Willo's final move
+//! of the turn, and before the player can give any more input.
+use crate::{
+    graveyard::willo::WilloSets,
+    history::{History, HistoryPlugin},
+    sokoban::SokobanSets,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+/// Sets used by turn systems.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum TurnSets {
+    /// Set for the system that counts turns via [TurnCount].
+    CountTurns,
+    /// Set for systems that take a step for their actors after every turn.
+    ///
+    /// Only runs on frames where a [TurnEnded] event can be read.
+    /// Runs before [SokobanSets::LogicalMovement], so sokoban commands sent by actors are resolved
+    /// in the same frame, before history can be recorded for the next turn.
+    ActorStep,
+}
+
+/// Plugin providing a turn structure for the graveyard.
+pub struct TurnPlugin;
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TurnEnded>()
+            .add_plugins(HistoryPlugin::<TurnCount, _>::run_in_state(
+                GameState::Graveyard,
+            ))
+            .configure_sets(
+                Update,
+                (
+                    (TurnSets::CountTurns, TurnSets::ActorStep)
+                        .chain()
+                        .run_if(in_state(GameState::Graveyard).and(on_event::<TurnEnded>))
+                        .before(SokobanSets::LogicalMovement),
+                    SokobanSets::MoveResolution.before(WilloSets::Input),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    spawn_turn_count.run_if(in_state(GameState::LevelTransition)),
+                    count_turns.in_set(TurnSets::CountTurns),
+                ),
+            );
+    }
+}
+
+/// Event that fires when Willo finishes a grave action and returns to waiting for input.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Event)]
+pub struct TurnEnded;
+
+/// Component on the level entity that counts the turns taken in the level so far.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deref, DerefMut, Component)]
+pub struct TurnCount(pub u32);
+
+fn spawn_turn_count(mut commands: Commands, levels: Query<Entity, Added<LevelIid>>) {
+    for level_entity in levels.iter() {
+        commands
+            .entity(level_entity)
+            .insert((TurnCount::default(), History::<TurnCount>::default()));
+    }
+}
+
+fn count_turns(
+    mut turn_ended_events: EventReader<TurnEnded>,
+    mut turn_counts: Query<&mut TurnCount>,
+) {
+    let turns = turn_ended_events.read().count() as u32;
+
+    for mut turn_count in turn_counts.iter_mut() {
+        **turn_count += turns;
+    }
+}
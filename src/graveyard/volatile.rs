@@ -1,11 +1,17 @@
 //! Plugin providing the core logic for [`Volatile`] entities.
 //!
 //! Volatile entities are "Solid" initially.
-//! Once they come into contact with another Volatile entity - they are both "Sublimated".
+//! Once they come into contact with another Volatile entity - they react.
+//! By default, they are both "Sublimated".
+//!
+//! Volatiles with a [`VolatileMaterial`] can react differently, according to the [`ReactionTable`].
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs::File, io::BufReader};
 
 use crate::{
+    graveyard::carry::Carried,
     history::{FlushHistoryCommands, HistoryPlugin},
     utils::any_match_filter,
     GameState,
@@ -14,7 +20,8 @@ use crate::{
 /// Plugin providing the core logic for [`Volatile`] entities.
 ///
 /// Volatile entities are "Solid" initially.
-/// Once they come into contact with another Volatile entity - they are both "Sublimated".
+/// Once they come into contact with another Volatile entity - they react.
+/// By default, they are both "Sublimated".
 pub struct VolatilePlugin;
 
 /// `SystemSet` performing sublimation of [`Volatile`] entities.
@@ -23,9 +30,11 @@ pub struct Sublimation;
 
 impl Plugin for VolatilePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(HistoryPlugin::<Volatile, _>::run_in_state(
-            GameState::Graveyard,
+        app.add_plugins((
+            HistoryPlugin::<Volatile, _>::run_in_state(GameState::Graveyard),
+            HistoryPlugin::<VolatileMaterial, _>::run_in_state(GameState::Graveyard),
         ))
+        .init_resource::<ReactionTable>()
         .add_systems(
             Update,
             sublimation
@@ -60,6 +69,119 @@ impl Volatile {
     pub fn sublimate(&mut self) {
         *self = Volatile::Sublimated;
     }
+
+    /// Sets this instance back to [`Volatile::Solid`].
+    pub fn resolidify(&mut self) {
+        *self = Volatile::Solid;
+    }
+}
+
+/// Component defining the material of a [`Volatile`] entity.
+///
+/// The material decides how the entity reacts with other volatiles via the [`ReactionTable`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Component, Deref, DerefMut)]
+pub struct VolatileMaterial(pub String);
+
+impl VolatileMaterial {
+    /// Construct a new [`VolatileMaterial`] with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        VolatileMaterial(name.into())
+    }
+}
+
+/// Enumerates the possible outcomes of a volatile solid entering another volatile solid.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Reaction {
+    /// Both volatiles are sublimated.
+    #[default]
+    BothVanish,
+    /// Only the moving volatile is sublimated.
+    MoverVanishes,
+    /// The moving volatile changes into the given material, if it has a material.
+    TransformMover(String),
+    /// The entered volatile changes into the given material, if it has a material.
+    TransformTarget(String),
+    /// Nothing happens, both volatiles remain solid.
+    Nothing,
+}
+
+/// Resource defining the [`Reaction`] when a material enters another material.
+///
+/// Indexed by the mover's material first, then the entered material.
+/// Any pair missing from the table, including volatiles without a [`VolatileMaterial`], reacts
+/// with [`Reaction::BothVanish`].
+#[derive(
+    Clone, Debug, Default, PartialEq, Eq, Deref, DerefMut, Serialize, Deserialize, Resource,
+)]
+pub struct ReactionTable(HashMap<String, HashMap<String, Reaction>>);
+
+static FALLBACK_REACTION: Reaction = Reaction::BothVanish;
+
+impl ReactionTable {
+    /// Returns the reaction of the `mover` material entering the `target` material.
+    pub fn reaction(
+        &self,
+        mover: Option<&VolatileMaterial>,
+        target: Option<&VolatileMaterial>,
+    ) -> &Reaction {
+        mover
+            .zip(target)
+            .and_then(|(mover, target)| self.get(&mover.0)?.get(&target.0))
+            .unwrap_or(&FALLBACK_REACTION)
+    }
+}
+
+/// Loads the [`ReactionTable`] from `settings/reactions.json`.
+pub fn load_reaction_table(asset_folder: String) -> std::io::Result<ReactionTable> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Ok(serde_json::from_reader(BufReader::new(File::open(
+            format!("{asset_folder}/../settings/reactions.json"),
+        )?))?)
+    }
+
+    // placed in a `#[cfg]` block rather than `if cfg!` so that changes to the file don't
+    // recompile non-wasm builds.
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(serde_json::from_str(include_str!(
+            "../../settings/reactions.json"
+        ))?)
+    }
+}
+
+type VolatileItem<'a> = (
+    Entity,
+    &'a GridCoords,
+    Mut<'a, Volatile>,
+    Option<Mut<'a, VolatileMaterial>>,
+);
+
+/// Applies the reaction of the `mover` volatile entering the `target` volatile.
+///
+/// Both volatiles should be solid and occupy the same space.
+fn react(reaction_table: &ReactionTable, mover: &mut VolatileItem, target: &mut VolatileItem) {
+    let (_, _, mover_volatile, mover_material) = mover;
+    let (_, _, target_volatile, target_material) = target;
+
+    match reaction_table.reaction(mover_material.as_deref(), target_material.as_deref()) {
+        Reaction::BothVanish => {
+            mover_volatile.sublimate();
+            target_volatile.sublimate();
+        }
+        Reaction::MoverVanishes => mover_volatile.sublimate(),
+        Reaction::TransformMover(material) => {
+            if let Some(mover_material) = mover_material {
+                mover_material.0 = material.clone();
+            }
+        }
+        Reaction::TransformTarget(material) => {
+            if let Some(target_material) = target_material {
+                target_material.0 = material.clone();
+            }
+        }
+        Reaction::Nothing => {}
+    }
 }
 
 /// System performing sublimation logic.
@@ -68,9 +190,20 @@ impl Volatile {
 /// This is so it can split moving volatiles and stationary volatiles into separate collections.
 /// This allows us to limit our collision detection to checking moved-volatiles against all-volatiles,
 /// rather than all-volatiles against all-volatiles.
+///
+/// Carried gravestones are off the board, so they're left out.
 fn sublimation(
     moved_volatile_entities: Query<(), (With<Volatile>, Changed<GridCoords>)>,
-    mut all_volatiles: Query<(Entity, &GridCoords, &mut Volatile)>,
+    mut all_volatiles: Query<
+        (
+            Entity,
+            &GridCoords,
+            &mut Volatile,
+            Option<&mut VolatileMaterial>,
+        ),
+        Without<Carried>,
+    >,
+    reaction_table: Res<ReactionTable>,
 ) {
     // Split volatiles into moved and stationary collections.
     let (mut moved_volatiles, mut stationary_volatiles): (Vec<_>, Vec<_>) = all_volatiles
@@ -78,29 +211,24 @@ fn sublimation(
         .partition(|(entity, ..)| moved_volatile_entities.contains(*entity));
 
     // Check for collisions between moved volatiles.
-    for index in 0..moved_volatiles.len() - 1 {
-        if let [(_, grid_coords_a, volatile_a), remaining_moved_volatiles @ ..] =
-            &mut moved_volatiles[index..]
-        {
-            if volatile_a.is_solid() {
-                for (_, grid_coords_b, volatile_b) in remaining_moved_volatiles.iter_mut() {
-                    if volatile_b.is_solid() && grid_coords_a == grid_coords_b {
-                        volatile_a.sublimate();
-                        volatile_b.sublimate();
-                    }
+    for index in 0..moved_volatiles.len() {
+        if let [volatile_a, remaining_moved_volatiles @ ..] = &mut moved_volatiles[index..] {
+            for volatile_b in remaining_moved_volatiles.iter_mut() {
+                if volatile_a.2.is_solid()
+                    && volatile_b.2.is_solid()
+                    && volatile_a.1 == volatile_b.1
+                {
+                    react(&reaction_table, volatile_a, volatile_b);
                 }
             }
         }
     }
 
     // Check for collisions between moved volatiles and stationary volatiles.
-    for (_, grid_coords_a, volatile_a) in moved_volatiles.iter_mut() {
-        if volatile_a.is_solid() {
-            for (_, grid_coords_b, volatile_b) in stationary_volatiles.iter_mut() {
-                if volatile_b.is_solid() && grid_coords_a == grid_coords_b {
-                    volatile_a.sublimate();
-                    volatile_b.sublimate();
-                }
+    for volatile_a in moved_volatiles.iter_mut() {
+        for volatile_b in stationary_volatiles.iter_mut() {
+            if volatile_a.2.is_solid() && volatile_b.2.is_solid() && volatile_a.1 == volatile_b.1 {
+                react(&reaction_table, volatile_a, volatile_b);
             }
         }
     }
@@ -377,4 +505,92 @@ mod tests {
             .iter(&app.world())
             .all(|volatile| volatile == &Volatile::Sublimated));
     }
+
+    fn spawn_materials(app: &mut App, reactions: &[(&str, &str, Reaction)]) -> [Entity; 2] {
+        let mut reaction_table = ReactionTable::default();
+        for (mover, target, reaction) in reactions {
+            reaction_table
+                .entry(mover.to_string())
+                .or_default()
+                .insert(target.to_string(), reaction.clone());
+        }
+        app.insert_resource(reaction_table);
+
+        let entities = [("HolyWater", 0), ("Fire", 1)].map(|(material, y)| {
+            app.world_mut()
+                .spawn((
+                    GridCoords { x: 0, y },
+                    Volatile::Solid,
+                    VolatileMaterial::new(material),
+                ))
+                .id()
+        });
+
+        app.update();
+
+        entities
+    }
+
+    #[test]
+    fn mover_vanishes_reaction_keeps_target() {
+        let mut app = app_setup();
+
+        let [bottom, top] =
+            spawn_materials(&mut app, &[("HolyWater", "Fire", Reaction::MoverVanishes)]);
+
+        app.world_mut().get_mut::<GridCoords>(bottom).unwrap().y += 1;
+
+        app.update();
+
+        assert_eq!(
+            app.world().get::<Volatile>(bottom).unwrap(),
+            &Volatile::Sublimated
+        );
+        assert_eq!(app.world().get::<Volatile>(top).unwrap(), &Volatile::Solid);
+    }
+
+    #[test]
+    fn transform_target_reaction_changes_material() {
+        let mut app = app_setup();
+
+        let [bottom, top] = spawn_materials(
+            &mut app,
+            &[(
+                "HolyWater",
+                "Fire",
+                Reaction::TransformTarget("Fog".to_string()),
+            )],
+        );
+
+        app.world_mut().get_mut::<GridCoords>(bottom).unwrap().y += 1;
+
+        app.update();
+
+        assert!(app
+            .world_mut()
+            .query::<&Volatile>()
+            .iter(app.world())
+            .all(|volatile| volatile == &Volatile::Solid));
+        assert_eq!(
+            app.world().get::<VolatileMaterial>(top).unwrap(),
+            &VolatileMaterial::new("Fog")
+        );
+    }
+
+    #[test]
+    fn missing_reaction_sublimates_both() {
+        let mut app = app_setup();
+
+        let [bottom, _] = spawn_materials(&mut app, &[]);
+
+        app.world_mut().get_mut::<GridCoords>(bottom).unwrap().y += 1;
+
+        app.update();
+
+        assert!(app
+            .world_mut()
+            .query::<&Volatile>()
+            .iter(app.world())
+            .all(|volatile| volatile == &Volatile::Sublimated));
+    }
 }
@@ -1,33 +1,128 @@
 //! Plugin providing spawning logic for static walls such as bushes and fences.
-use crate::sokoban::SokobanBlock;
-use bevy::prelude::*;
+//!
+//! The IntGrid values treated as walls, and their collision layers, are configured in
+//! `settings/walls.json`.
+use crate::{
+    graveyard::collision::CollisionLayerSettings,
+    sokoban::{CollisionLayers, SokobanBlock},
+};
+use bevy::{
+    ecs::{component::HookContext, world::DeferredWorld},
+    prelude::*,
+};
 use bevy_ecs_ldtk::prelude::*;
+use serde::Deserialize;
+use std::{collections::HashMap, fs::File, io::BufReader};
 
 /// Plugin providing spawning logic for static walls such as bushes and fences.
 pub struct WallPlugin;
 
-const WALL_INT_GRID_VALUES: &[i32] = &[1, 3, 4];
-
 impl Plugin for WallPlugin {
     fn build(&self, app: &mut App) {
-        WALL_INT_GRID_VALUES.iter().for_each(|value| {
+        let asset_folder = app.get_added_plugins::<AssetPlugin>()[0].file_path.clone();
+
+        let wall_settings = load_wall_settings(asset_folder).expect("unable to load wall settings");
+
+        wall_settings.keys().for_each(|value| {
             app.register_ldtk_int_cell::<WallBundle>(*value);
         });
+
+        app.insert_resource(wall_settings);
+    }
+}
+
+/// Resource mapping wall IntGrid values to their collision layers.
+#[derive(Clone, Debug, Default, Deref, DerefMut, Deserialize, Resource)]
+struct WallSettings(HashMap<i32, CollisionLayerSettings>);
+
+fn load_wall_settings(asset_folder: String) -> std::io::Result<WallSettings> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Ok(serde_json::from_reader(BufReader::new(File::open(
+            format!("{asset_folder}/../settings/walls.json"),
+        )?))?)
+    }
+
+    // placed in a `#[cfg]` block rather than `if cfg!` so that changes to the file don't
+    // recompile non-wasm builds.
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(serde_json::from_str(include_str!(
+            "../../settings/walls.json"
+        ))?)
+    }
+}
+
+/// Component storing the IntGrid value of a wall.
+///
+/// Once added, it gives the wall its [CollisionLayers] from the [WallSettings].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Component)]
+#[component(on_add = set_wall_collision_layers)]
+struct Wall(i32);
+
+impl From<IntGridCell> for Wall {
+    fn from(cell: IntGridCell) -> Wall {
+        Wall(cell.value)
     }
 }
 
-#[derive(Clone, Bundle, LdtkIntCell)]
+fn set_wall_collision_layers(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(Wall(value)) = world.get::<Wall>(entity).copied() else {
+        return;
+    };
+
+    let Some(layers) = world
+        .get_resource::<WallSettings>()
+        .and_then(|settings| settings.get(&value))
+        .map(CollisionLayers::from)
+    else {
+        return;
+    };
+
+    if let Some(mut collision_layers) = world.get_mut::<CollisionLayers>(entity) {
+        *collision_layers = layers;
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkIntCell)]
 struct WallBundle {
+    #[from_int_grid_cell]
+    wall: Wall,
     #[from_int_grid_cell]
     sokoban_block: SokobanBlock,
+    collision_layers: CollisionLayers,
 }
 
 impl From<IntGridCell> for SokobanBlock {
-    fn from(cell: IntGridCell) -> SokobanBlock {
-        if WALL_INT_GRID_VALUES.contains(&cell.value) {
-            SokobanBlock::Static
-        } else {
-            panic!("tried to give non-wall cell a SokobanBlock")
-        }
+    fn from(_: IntGridCell) -> SokobanBlock {
+        SokobanBlock::Static
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graveyard::collision::CollisionLayer;
+
+    #[test]
+    fn walls_spawn_with_their_collision_layers() {
+        let mut world = World::new();
+        world.insert_resource(
+            serde_json::from_str::<WallSettings>(r#"{ "1": { "memberships": ["Bush"] } }"#)
+                .unwrap(),
+        );
+
+        let bush = world
+            .spawn(WallBundle {
+                wall: Wall(1),
+                sokoban_block: SokobanBlock::Static,
+                collision_layers: CollisionLayers::default(),
+            })
+            .id();
+
+        assert_eq!(
+            world.get::<CollisionLayers>(bush),
+            Some(&CollisionLayers::new(CollisionLayer::Bush.bit(), u32::MAX))
+        );
     }
 }
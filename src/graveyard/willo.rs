@@ -2,9 +2,20 @@
 use crate::{
     animation::{FromComponentAnimator, SpriteSheetAnimation},
     from_component::FromComponentSet,
-    graveyard::{exorcism::ExorcismEvent, gravestone::GraveId, volatile::Volatile},
+    graveyard::{
+        arrow_block::Stride,
+        carry::Carrying,
+        collision::willo_collision_layers,
+        exorcism::ExorcismEvent,
+        gravestone::GraveId,
+        turn::TurnEnded,
+        volatile::{Volatile, VolatileMaterial},
+    },
     history::{FlushHistoryCommands, History, HistoryCommands, HistoryPlugin},
-    sokoban::{Direction, PushEvent, PushTracker, SokobanBlock, SokobanCommands, SokobanSets},
+    sokoban::{
+        CollisionLayers, Direction, PushEvent, PushTracker, SokobanBlock, SokobanCommands,
+        SokobanSets,
+    },
     AssetHolder, GameState, UNIT_LENGTH,
 };
 use bevy::prelude::*;
@@ -79,6 +90,15 @@ pub enum WilloState {
     ///
     /// This move is defined by the file of the gravestone on the movement table.
     FileMove(GraveId),
+    /// Willo is performing the optional third part of a grave action.
+    ///
+    /// This move is defined by the anti-diagonal of the gravestone on the movement table, and is
+    /// skipped when that anti-diagonal has no arrow blocks.
+    DiagonalMove(GraveId),
+    /// Willo has sent the final move of a grave action, and is waiting for it to be resolved.
+    ///
+    /// The turn ends once it is, so other actors take their step from Willo's final position.
+    EndingTurn,
 }
 
 /// Component enumerating the possible states of Willo's animation.
@@ -160,6 +180,8 @@ struct WilloBundle {
     history: History<GridCoords>,
     #[with(SokobanBlock::new_dynamic)]
     sokoban_block: SokobanBlock,
+    #[with(willo_collision_layers)]
+    collision_layers: CollisionLayers,
     push_tracker: PushTracker,
     willo_state: WilloState,
     movement_timer: MovementTimer,
@@ -168,26 +190,38 @@ struct WilloBundle {
     willo_animation_state: WilloAnimationState,
     volatile: Volatile,
     volatile_history: History<Volatile>,
+    #[with(willo_material)]
+    material: VolatileMaterial,
+    material_history: History<VolatileMaterial>,
+    carrying: Carrying,
+    carrying_history: History<Carrying>,
+}
+
+fn willo_material(_: &EntityInstance) -> VolatileMaterial {
+    VolatileMaterial::new("Willo")
 }
 
 fn push_sugar(
     mut commands: Commands,
     mut push_events: EventReader<PushEvent<Direction>>,
-    mut willo_query: Query<(Entity, &mut WilloAnimationState)>,
+    mut willo_query: Query<&mut WilloAnimationState>,
     sfx: Res<AssetHolder>,
 ) {
-    let (willo_entity, mut animation_state) = willo_query
-        .single_mut()
-        .expect("willo should exist in this system");
-    for PushEvent { direction, .. } in push_events
-        .read()
-        .filter(|PushEvent { pusher, .. }| *pusher == willo_entity)
-    {
+    let mut pushed = false;
+
+    for PushEvent { pusher, direction } in push_events.read() {
+        if let Ok(mut animation_state) = willo_query.get_mut(*pusher) {
+            *animation_state = WilloAnimationState::Push(*direction);
+            pushed = true;
+        }
+    }
+
+    // several willos pushing at once should still only make one sound
+    if pushed {
         commands.spawn((
             AudioPlayer::new(sfx.push_sound.clone()),
             PlaybackSettings::DESPAWN,
         ));
-        *animation_state = WilloAnimationState::Push(*direction);
     }
 }
 
@@ -198,7 +232,7 @@ fn push_translation(
         Changed<WilloAnimationState>,
     >,
 ) {
-    if let Ok((entity, &grid_coords, transform, animation_state)) = willo_query.single() {
+    for (entity, &grid_coords, transform, animation_state) in willo_query.iter() {
         let xy = grid_coords_to_translation(grid_coords, IVec2::splat(UNIT_LENGTH))
             + match animation_state {
                 WilloAnimationState::Push(direction) => (IVec2::ZERO + direction).as_vec2() * 5.,
@@ -224,10 +258,9 @@ fn history_sugar(
     for command in history_commands.read() {
         match command {
             HistoryCommands::Rewind | HistoryCommands::Reset => {
-                *willo_query
-                    .single_mut()
-                    .expect("willo should exist during this system") =
-                    WilloAnimationState::Idle(Direction::Down);
+                for mut animation_state in willo_query.iter_mut() {
+                    *animation_state = WilloAnimationState::Idle(Direction::Down);
+                }
                 commands.spawn((
                     AudioPlayer::new(sfx.undo_sound.clone()),
                     PlaybackSettings::DESPAWN,
@@ -238,52 +271,320 @@ fn history_sugar(
     }
 }
 
-fn play_exorcism_animaton(mut willo_query: Query<&mut WilloAnimationState>) {
-    if let Ok(mut animation_state) = willo_query.single_mut() {
-        *animation_state = WilloAnimationState::Dying;
+fn play_exorcism_animaton(
+    mut exorcism_events: EventReader<ExorcismEvent>,
+    mut willo_query: Query<&mut WilloAnimationState>,
+) {
+    for ExorcismEvent { willo_entity } in exorcism_events.read() {
+        if let Ok(mut animation_state) = willo_query.get_mut(*willo_entity) {
+            *animation_state = WilloAnimationState::Dying;
+        }
     }
 }
 
+/// Sends the sokoban commands for one component of a movement.
+///
+/// Multi-cell pushes are sent as successive single-cell moves, so each one can push on its own.
+fn stride_willo(
+    sokoban_commands: &mut SokobanCommands<Direction>,
+    entity: Entity,
+    direction: Direction,
+    stride: &Stride,
+) {
+    match stride {
+        Stride::Push(distance) => {
+            for _ in 0..*distance {
+                sokoban_commands.move_block(entity, direction);
+            }
+        }
+        Stride::Jump(distance) => sokoban_commands.jump_block(entity, direction, *distance),
+    }
+}
+
+/// Returns the key that orders simultaneous moves, like those of several willos.
+///
+/// Blocks furthest along the direction of their move come first, so they get out of the way
+/// before the blocks trailing them move, rather than being pushed by them.
+/// Ties are broken by position, so the order never depends on entity order.
+pub fn front_first_key(grid_coords: &GridCoords, direction: &Direction) -> (i32, i32, i32) {
+    let coords = IVec2::from(*grid_coords);
+    (-(IVec2::ZERO + direction).dot(coords), coords.x, coords.y)
+}
+
 fn move_willo_by_tiles(
     mut willo_query: Query<(
         Entity,
+        &GridCoords,
         &mut MovementTimer,
         &mut WilloState,
         &mut WilloAnimationState,
     )>,
     gravestone_movement_queries: GravestoneMovementQueries,
     mut sokoban_commands: SokobanCommands<Direction>,
+    mut turn_ended_events: EventWriter<TurnEnded>,
     time: Res<Time>,
 ) {
-    if let Ok((entity, mut timer, mut willo_state, mut willo_animation_state)) =
-        willo_query.single_mut()
+    let mut moves = Vec::new();
+    let mut turn_ended = false;
+
+    for (entity, grid_coords, mut timer, mut willo_state, mut willo_animation_state) in
+        willo_query.iter_mut()
     {
+        // the final move was sent last frame, and has been resolved before this system runs
+        if *willo_state == WilloState::EndingTurn {
+            *willo_state = WilloState::Waiting;
+            turn_ended = true;
+            continue;
+        }
+
         timer.0.tick(time.delta());
 
-        if timer.0.finished() {
-            match willo_state.as_ref() {
-                WilloState::RankMove(key) => {
-                    if let Some(movement_tile) = gravestone_movement_queries.find_movement(key) {
-                        let direction = movement_tile.row_move();
-                        sokoban_commands.move_block(entity, *direction);
-                        *willo_animation_state = WilloAnimationState::Idle(*direction);
-                    }
-
-                    *willo_state = WilloState::FileMove(*key);
-                    timer.0.reset();
+        if !timer.0.finished() {
+            continue;
+        }
+
+        match *willo_state {
+            WilloState::RankMove(key) => {
+                if let Some(movement_tile) = gravestone_movement_queries.find_movement(&key) {
+                    let direction = *movement_tile.row_move();
+                    moves.push((entity, *grid_coords, direction, *movement_tile.row_stride()));
+                    *willo_animation_state = WilloAnimationState::Idle(direction);
+                }
+
+                *willo_state = WilloState::FileMove(key);
+                timer.0.reset();
+            }
+            WilloState::FileMove(key) => {
+                let mut has_diagonal_move = false;
+
+                if let Some(movement_tile) = gravestone_movement_queries.find_movement(&key) {
+                    let direction = *movement_tile.column_move();
+                    moves.push((
+                        entity,
+                        *grid_coords,
+                        direction,
+                        *movement_tile.column_stride(),
+                    ));
+                    *willo_animation_state = WilloAnimationState::Idle(direction);
+
+                    has_diagonal_move = *movement_tile.diagonal_move() != Direction::Zero;
                 }
-                WilloState::FileMove(key) => {
-                    if let Some(movement_tile) = gravestone_movement_queries.find_movement(key) {
-                        let direction = movement_tile.column_move();
-                        sokoban_commands.move_block(entity, *direction);
-                        *willo_animation_state = WilloAnimationState::Idle(*direction);
-                    }
-
-                    *willo_state = WilloState::Waiting;
-                    timer.0.reset();
+
+                timer.0.reset();
+
+                if has_diagonal_move {
+                    *willo_state = WilloState::DiagonalMove(key);
+                } else {
+                    *willo_state = WilloState::EndingTurn;
+                }
+            }
+            WilloState::DiagonalMove(key) => {
+                if let Some(movement_tile) = gravestone_movement_queries.find_movement(&key) {
+                    let direction = *movement_tile.diagonal_move();
+                    moves.push((
+                        entity,
+                        *grid_coords,
+                        direction,
+                        *movement_tile.diagonal_stride(),
+                    ));
+                    *willo_animation_state = WilloAnimationState::Idle(direction);
                 }
-                _ => {}
+
+                *willo_state = WilloState::EndingTurn;
+                timer.0.reset();
             }
+            _ => {}
         }
     }
+
+    moves.sort_by_key(|(_, grid_coords, direction, _)| front_first_key(grid_coords, direction));
+
+    for (entity, _, direction, stride) in moves {
+        stride_willo(&mut sokoban_commands, entity, direction, &stride);
+    }
+
+    if turn_ended {
+        turn_ended_events.write(TurnEnded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        animation::AnimationEvent,
+        graveyard::{
+            arrow_block::{MovementTile, Stride},
+            turn::{TurnPlugin, TurnSets},
+        },
+        sokoban::SokobanPlugin,
+    };
+    use bevy::state::app::StatesPlugin;
+
+    #[test]
+    fn front_willo_moves_first() {
+        let mut willos = [
+            GridCoords::new(1, 1),
+            GridCoords::new(3, 1),
+            GridCoords::new(2, 1),
+            GridCoords::new(3, 0),
+        ];
+
+        willos.sort_by_key(|grid_coords| front_first_key(grid_coords, &Direction::Right));
+
+        assert_eq!(
+            willos,
+            [
+                GridCoords::new(3, 0),
+                GridCoords::new(3, 1),
+                GridCoords::new(2, 1),
+                GridCoords::new(1, 1),
+            ]
+        );
+    }
+
+    /// Positions Willo was seen at by actors taking their step.
+    #[derive(Default, Resource)]
+    struct ActorSteps(Vec<GridCoords>);
+
+    fn record_actor_step(
+        willo_query: Query<&GridCoords, With<WilloState>>,
+        mut actor_steps: ResMut<ActorSteps>,
+    ) {
+        actor_steps.0.extend(willo_query.iter());
+    }
+
+    fn app_setup() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .init_resource::<Time>()
+            .init_resource::<ActorSteps>()
+            .add_event::<AnimationEvent>()
+            .add_event::<ExorcismEvent>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                TurnPlugin,
+                WilloPlugin,
+            ))
+            .add_systems(Update, record_actor_step.in_set(TurnSets::ActorStep))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        app
+    }
+
+    /// Spawns the Q gravestone on the given movement tile, and a Willo at (0, 0) acting on it.
+    fn spawn_willo_acting_on(app: &mut App, movement_tile: MovementTile) -> Entity {
+        app.world_mut().spawn((
+            GridCoords::new(5, 5),
+            GraveId('Q'),
+            Volatile::Solid,
+            movement_tile,
+        ));
+        app.world_mut()
+            .spawn((
+                GridCoords::new(0, 0),
+                SokobanBlock::Dynamic,
+                WilloState::RankMove(GraveId('Q')),
+                WilloAnimationState::Idle(Direction::Down),
+                // a zero-length timer lets Willo move on every frame
+                MovementTimer(Timer::from_seconds(0., TimerMode::Once)),
+            ))
+            .id()
+    }
+
+    #[test]
+    fn turn_ends_once_the_final_move_is_resolved() {
+        let mut app = app_setup();
+        let willo =
+            spawn_willo_acting_on(&mut app, MovementTile::new(Direction::Right, Direction::Up));
+
+        for _ in 0..6 {
+            app.update();
+        }
+
+        assert_eq!(
+            app.world().get::<WilloState>(willo),
+            Some(&WilloState::Waiting)
+        );
+        assert_eq!(
+            app.world().resource::<ActorSteps>().0,
+            vec![GridCoords::new(1, 1)]
+        );
+    }
+
+    #[test]
+    fn diagonal_move_follows_the_file_move() {
+        let mut app = app_setup();
+        let willo = spawn_willo_acting_on(
+            &mut app,
+            MovementTile::new(Direction::Right, Direction::Up)
+                .with_diagonal(Direction::Left, Stride::Push(2)),
+        );
+
+        let mut states = Vec::new();
+        for _ in 0..6 {
+            app.update();
+            states.push(*app.world().get::<WilloState>(willo).unwrap());
+        }
+        states.dedup();
+
+        assert_eq!(
+            states,
+            vec![
+                WilloState::FileMove(GraveId('Q')),
+                WilloState::DiagonalMove(GraveId('Q')),
+                WilloState::EndingTurn,
+                WilloState::Waiting,
+            ]
+        );
+        assert_eq!(
+            app.world().get::<GridCoords>(willo),
+            Some(&GridCoords::new(-1, 1))
+        );
+        assert_eq!(
+            app.world().resource::<ActorSteps>().0,
+            vec![GridCoords::new(-1, 1)]
+        );
+    }
+
+    #[test]
+    fn strides_carry_willo_several_cells() {
+        let mut app = app_setup();
+        let jumped = app
+            .world_mut()
+            .spawn((GridCoords::new(1, 0), SokobanBlock::Dynamic))
+            .id();
+        let pushed = app
+            .world_mut()
+            .spawn((GridCoords::new(2, 1), SokobanBlock::Dynamic))
+            .id();
+        let willo = spawn_willo_acting_on(
+            &mut app,
+            MovementTile::new(Direction::Right, Direction::Up)
+                .with_strides(Stride::Jump(2), Stride::Push(2)),
+        );
+
+        for _ in 0..6 {
+            app.update();
+        }
+
+        assert_eq!(
+            app.world().get::<GridCoords>(willo),
+            Some(&GridCoords::new(2, 2))
+        );
+        assert_eq!(
+            app.world().get::<GridCoords>(jumped),
+            Some(&GridCoords::new(1, 0))
+        );
+        assert_eq!(
+            app.world().get::<GridCoords>(pushed),
+            Some(&GridCoords::new(2, 3))
+        );
+    }
 }
@@ -1,23 +1,95 @@
-//! Plugin providing the wind systems and components that react to it (currently just grass).
-use crate::GameState;
+//! Plugin providing the wind systems and components that react to it.
+//!
+//! Levels can have gusts of wind, defined by the "GustDirection" LDtk level enum field and the
+//! "GustEvery" LDtk level int field.
+//! The optional "GustOffset" LDtk level int field delays the first gust by some turns.
+//! When a gust blows, every [SokobanBlock::Dynamic] block with an open tile in the gust direction
+//! is moved one tile along with it, and the grass is blown over.
+use crate::{
+    graveyard::turn::{TurnCount, TurnSets},
+    sokoban::{CollisionLayers, Direction, SokobanBlock, SokobanCommands, WrapAround},
+    AssetHolder, GameState,
+};
 use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
 use rand::Rng;
 use std::cmp;
 
-/// Plugin providing the wind systems and components that react to it (currently just grass).
+/// Plugin providing the wind systems and components that react to it.
 pub struct WindPlugin;
 
 impl Plugin for WindPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            animate_grass_system.run_if(not(in_state(GameState::AssetLoading))),
-        )
-        .register_ldtk_entity::<GrassBundle>("Grass");
+        app.add_event::<GustEvent>()
+            .add_systems(
+                Update,
+                (
+                    spawn_gusts.run_if(in_state(GameState::LevelTransition)),
+                    blow_gusts.in_set(TurnSets::ActorStep),
+                    blow_grass
+                        .run_if(on_event::<GustEvent>)
+                        .after(TurnSets::ActorStep)
+                        .before(animate_grass_system),
+                    animate_grass_system.run_if(not(in_state(GameState::AssetLoading))),
+                ),
+            )
+            .register_ldtk_entity::<GrassBundle>("Grass");
     }
 }
 
+/// Component on the level entity defining when, and in which direction, the wind gusts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Component)]
+pub struct Gusts {
+    direction: Direction,
+    every: u32,
+    offset: u32,
+}
+
+impl Gusts {
+    /// Returns `true` if a gust blows once the given number of turns have been taken.
+    pub fn blows_after(&self, turns: u32) -> bool {
+        self.every > 0 && turns > self.offset && (turns - self.offset).is_multiple_of(self.every)
+    }
+
+    /// Returns the gusts of the given level, or `None` if it has no wind.
+    fn from_level(level: &Level) -> Option<Gusts> {
+        let direction = match level
+            .get_maybe_enum_field("GustDirection")
+            .ok()
+            .and_then(|direction| direction.as_deref())
+        {
+            None => return None,
+            Some("Up") => Direction::Up,
+            Some("Down") => Direction::Down,
+            Some("Left") => Direction::Left,
+            Some("Right") => Direction::Right,
+            Some(direction) => panic!("encountered bad gust direction: {direction}"),
+        };
+
+        let int_field = |identifier| {
+            level
+                .get_maybe_int_field(identifier)
+                .ok()
+                .copied()
+                .flatten()
+                .unwrap_or_default()
+                .max(0) as u32
+        };
+
+        Some(Gusts {
+            direction,
+            every: int_field("GustEvery"),
+            offset: int_field("GustOffset"),
+        })
+    }
+}
+
+/// Event that fires when a gust of wind blows.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Event)]
+struct GustEvent {
+    direction: Direction,
+}
+
 /// Component with timer defining spacing between animation updates of wind-reactive components.
 #[derive(Clone, Debug, Component)]
 struct WindTimer(Timer);
@@ -35,6 +107,115 @@ struct GrassBundle {
     wind_timer: WindTimer,
 }
 
+fn spawn_gusts(
+    mut commands: Commands,
+    levels: Query<(Entity, &LevelIid), Added<LevelIid>>,
+    ldtk_assets: Res<Assets<LdtkProject>>,
+    asset_holder: Res<AssetHolder>,
+) {
+    let Some(ldtk_asset) = ldtk_assets.get(&asset_holder.ldtk) else {
+        return;
+    };
+
+    for (level_entity, level_iid) in levels.iter() {
+        let level = ldtk_asset
+            .get_raw_level_by_iid(level_iid.get())
+            .expect("spawned level should exist in project");
+
+        if let Some(gusts) = Gusts::from_level(level) {
+            commands.entity(level_entity).insert(gusts);
+        }
+    }
+}
+
+/// Returns the dynamic blocks that a gust in the given direction moves.
+///
+/// Only blocks with an open tile in the gust direction are moved, so gusts never push.
+fn gusted_blocks(
+    blocks: &[(Entity, GridCoords, SokobanBlock, CollisionLayers)],
+    direction: Direction,
+    wrap_around: &WrapAround,
+) -> Vec<Entity> {
+    blocks
+        .iter()
+        .filter(|(_, _, block, _)| *block == SokobanBlock::Dynamic)
+        .filter(|(entity, grid_coords, _, layers)| {
+            let destination =
+                GridCoords::from(wrap_around.wrap(IVec2::from(*grid_coords) + &direction));
+
+            !blocks
+                .iter()
+                .any(|(other_entity, other_grid_coords, _, other_layers)| {
+                    other_entity != entity
+                        && *other_grid_coords == destination
+                        && layers.interacts_with(other_layers)
+                })
+        })
+        .map(|(entity, ..)| *entity)
+        .collect()
+}
+
+fn blow_gusts(
+    levels: Query<(&Gusts, &TurnCount)>,
+    blocks: Query<(Entity, &GridCoords, &SokobanBlock, Option<&CollisionLayers>)>,
+    wrap_around: Res<WrapAround>,
+    mut sokoban_commands: SokobanCommands<Direction>,
+    mut gust_events: EventWriter<GustEvent>,
+) {
+    let blocks: Vec<_> = blocks
+        .iter()
+        .map(|(entity, grid_coords, block, layers)| {
+            (
+                entity,
+                *grid_coords,
+                *block,
+                layers.copied().unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    for (gusts, turn_count) in levels.iter() {
+        if !gusts.blows_after(**turn_count) {
+            continue;
+        }
+
+        for entity in gusted_blocks(&blocks, gusts.direction, &wrap_around) {
+            sokoban_commands.move_block(entity, gusts.direction);
+        }
+
+        gust_events.write(GustEvent {
+            direction: gusts.direction,
+        });
+    }
+}
+
+fn blow_grass(
+    mut gust_events: EventReader<GustEvent>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+    mut query: Query<(&mut WindTimer, &mut Sprite)>,
+) {
+    let Some(GustEvent { direction }) = gust_events.read().last() else {
+        return;
+    };
+
+    for (mut timer, mut sprite) in query.iter_mut() {
+        // hold the blown-over frame for a moment before the grass settles again
+        timer.0.reset();
+
+        match direction {
+            Direction::Left => sprite.flip_x = true,
+            Direction::Right => sprite.flip_x = false,
+            _ => (),
+        }
+
+        if let Some(texture_atlas) = &mut sprite.texture_atlas {
+            if let Some(texture_atlas_layout) = texture_atlases.get(&texture_atlas.layout) {
+                texture_atlas.index = texture_atlas_layout.len() - 1;
+            }
+        }
+    }
+}
+
 fn animate_grass_system(
     time: Res<Time>,
     texture_atlases: Res<Assets<TextureAtlasLayout>>,
@@ -58,3 +239,122 @@ fn animate_grass_system(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graveyard::turn::{TurnEnded, TurnPlugin},
+        sokoban::SokobanPlugin,
+    };
+    use bevy::state::app::StatesPlugin;
+
+    #[test]
+    fn gusts_blow_on_schedule() {
+        let gusts = Gusts {
+            direction: Direction::Right,
+            every: 3,
+            offset: 1,
+        };
+
+        let gust_turns: Vec<u32> = (0..10).filter(|turns| gusts.blows_after(*turns)).collect();
+
+        assert_eq!(gust_turns, vec![4, 7]);
+    }
+
+    #[test]
+    fn gusts_only_move_dynamic_blocks_with_open_tiles() {
+        let [open, blocked, wall, statue] = [0, 1, 2, 3].map(Entity::from_raw);
+
+        let blocks = [
+            (
+                open,
+                GridCoords::new(0, 1),
+                SokobanBlock::Dynamic,
+                CollisionLayers::ALL,
+            ),
+            (
+                blocked,
+                GridCoords::new(0, 0),
+                SokobanBlock::Dynamic,
+                CollisionLayers::ALL,
+            ),
+            (
+                wall,
+                GridCoords::new(1, 0),
+                SokobanBlock::Static,
+                CollisionLayers::ALL,
+            ),
+            (
+                statue,
+                GridCoords::new(3, 0),
+                SokobanBlock::Static,
+                CollisionLayers::ALL,
+            ),
+        ];
+
+        assert_eq!(
+            gusted_blocks(&blocks, Direction::Right, &WrapAround(None)),
+            vec![open]
+        );
+    }
+
+    #[test]
+    fn gusts_blow_once_their_turn_has_ended() {
+        let mut app = App::new();
+
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .init_resource::<Time>()
+            .init_resource::<Assets<TextureAtlasLayout>>()
+            .add_plugins((
+                SokobanPlugin::<GameState, SokobanBlock, Direction>::new(
+                    GameState::Graveyard,
+                    "IntGrid",
+                ),
+                TurnPlugin,
+                WindPlugin,
+            ))
+            .insert_resource(NextState::Pending(GameState::Graveyard));
+        app.update();
+
+        let level = app
+            .world_mut()
+            .spawn((
+                Gusts {
+                    direction: Direction::Right,
+                    every: 2,
+                    offset: 0,
+                },
+                TurnCount::default(),
+            ))
+            .id();
+        let block = app
+            .world_mut()
+            .spawn((GridCoords::new(0, 0), SokobanBlock::Dynamic))
+            .id();
+
+        app.world_mut().send_event(TurnEnded);
+        app.update();
+        assert_eq!(app.world().get::<TurnCount>(level), Some(&TurnCount(1)));
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(0, 0))
+        );
+
+        // the gust sees the turn it blows on already counted, and moves blocks in the same frame
+        app.world_mut().send_event(TurnEnded);
+        app.update();
+        assert_eq!(app.world().get::<TurnCount>(level), Some(&TurnCount(2)));
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(1, 0))
+        );
+
+        app.update();
+        assert_eq!(
+            app.world().get::<GridCoords>(block),
+            Some(&GridCoords::new(1, 0))
+        );
+    }
+}
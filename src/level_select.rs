@@ -1,11 +1,16 @@
 //! Plugin providing functionality for the level select card/menu.
 use crate::{
     event_scheduler::{EventScheduler, EventSchedulerPlugin},
-    graveyard::GraveyardAction,
+    graveyard::{
+        collectible::collectible_count,
+        multiplayer::{PlayMode, VersusRace},
+        GraveyardAction,
+    },
     level_transition::TransitionTo,
     nine_slice::{
         generate_nineslice_image, texture_atlas_from_nine_slice, NineSliceIndex, NineSliceSize,
     },
+    save::SaveData,
     ui::{
         action::{UiAction, UiActionPlugin},
         font_scale::{FontScale, FontSize},
@@ -37,6 +42,7 @@ impl Plugin for LevelSelectPlugin {
                     select_level
                         .run_if(in_state(GameState::LevelSelect))
                         .run_if(on_event::<UiAction<LevelSelectAction>>),
+                    update_play_mode_text.run_if(resource_changed::<PlayMode>),
                     despawn_level_select_card.run_if(on_event::<LevelSelectCardEvent>),
                 ),
             )
@@ -62,8 +68,13 @@ pub enum LevelSelectCardEvent {
 #[derive(Clone, Eq, PartialEq, Debug)]
 enum LevelSelectAction {
     GoToLevel(LevelSelection),
+    SetPlayMode(PlayMode),
 }
 
+/// Component that marks the text showing the current [PlayMode] on the level select card.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Component)]
+struct PlayModeText;
+
 fn level_select_card_style(position: UiRect) -> Node {
     let UiRect {
         left,
@@ -91,6 +102,9 @@ fn spawn_level_select_card(
     asset_holder: Res<AssetHolder>,
     mut images: ResMut<Assets<Image>>,
     ldtk_assets: Res<Assets<LdtkProject>>,
+    ldtk_level_assets: Res<Assets<LdtkExternalLevel>>,
+    play_mode: Res<PlayMode>,
+    save_data: Res<SaveData>,
     mut event_writer: EventWriter<LevelSelectCardEvent>,
 ) {
     // TODO: refactor this to avoid repeated code with spawn_level_card
@@ -147,6 +161,39 @@ fn spawn_level_select_card(
                 ))
                 .insert(FontScale::from(FontSize::Huge));
 
+            // spawn play mode text
+            parent
+                .spawn((
+                    Text(format!("Mode: {}", play_mode.name())),
+                    TextFont::from_font(asset_holder.font.clone()),
+                    TextColor(Color::WHITE),
+                ))
+                .insert(FontScale::from(FontSize::Small))
+                .insert(PlayModeText);
+
+            // spawn play mode button container
+            parent
+                .spawn((
+                    BackgroundColor(Color::NONE),
+                    Node {
+                        justify_content: JustifyContent::SpaceAround,
+                        width: Val::Percent(80.),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    for play_mode in [PlayMode::Solo, PlayMode::HotSeat, PlayMode::Versus] {
+                        text_button::spawn(
+                            parent,
+                            play_mode.name(),
+                            &asset_holder,
+                            Val::Percent(2.),
+                            FontSize::Small,
+                        )
+                        .insert(UiAction(LevelSelectAction::SetPlayMode(play_mode)));
+                    }
+                });
+
             // spawn level button container
             parent
                 .spawn((
@@ -168,10 +215,21 @@ fn spawn_level_select_card(
                 .with_children(|parent| {
                     // spawn a button for every level
                     if let Some(ldtk) = ldtk_assets.get(&asset_holder.ldtk) {
-                        for (i, _) in ldtk.iter_raw_levels().enumerate().skip(1) {
+                        for (i, level) in ldtk.iter_raw_levels().enumerate().skip(1) {
+                            let total = ldtk
+                                .as_parent()
+                                .get_external_level_by_iid(&ldtk_level_assets, &level.iid)
+                                .map_or(0, |level| collectible_count(level.raw()));
+                            let label = if total > 0 {
+                                let collected = save_data.collected_count(&level.iid);
+                                format!("#{i} ({collected}/{total})")
+                            } else {
+                                format!("#{i}")
+                            };
+
                             text_button::spawn(
                                 parent,
-                                format!("#{i}"),
+                                label,
                                 &asset_holder,
                                 Val::Percent(2.),
                                 FontSize::Medium,
@@ -204,11 +262,31 @@ fn select_level(
     mut commands: Commands,
     mut next_state: ResMut<NextState<GameState>>,
     mut ui_actions: EventReader<UiAction<LevelSelectAction>>,
+    mut play_mode: ResMut<PlayMode>,
 ) {
     for action in ui_actions.read() {
-        let UiAction(LevelSelectAction::GoToLevel(level_selection)) = action;
-        commands.insert_resource(TransitionTo(level_selection.clone()));
-        next_state.set(GameState::LevelTransition);
+        match action {
+            UiAction(LevelSelectAction::GoToLevel(level_selection)) => {
+                commands.insert_resource(TransitionTo(level_selection.clone()));
+                // a fresh level starts a fresh race
+                commands.insert_resource(VersusRace::default());
+                next_state.set(GameState::LevelTransition);
+            }
+            UiAction(LevelSelectAction::SetPlayMode(new_play_mode)) => {
+                if play_mode.set_if_neq(*new_play_mode) {
+                    commands.insert_resource(VersusRace::default());
+                }
+            }
+        }
+    }
+}
+
+fn update_play_mode_text(
+    play_mode: Res<PlayMode>,
+    mut play_mode_texts: Query<&mut Text, With<PlayModeText>>,
+) {
+    for mut text in play_mode_texts.iter_mut() {
+        text.0 = format!("Mode: {}", play_mode.name());
     }
 }
 
@@ -60,9 +60,16 @@ impl Plugin for LevelTransitionPlugin {
 }
 
 /// Resource that can be inserted to trigger a level transition.
+///
+/// Transitioning to the current level respawns it.
 #[derive(Clone, Eq, PartialEq, Debug, Default, Deref, DerefMut, Resource)]
 pub struct TransitionTo(pub LevelSelection);
 
+/// Resource that can be inserted alongside [TransitionTo] to show a message on the level card,
+/// like the result of a two-player match.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Deref, DerefMut, Resource)]
+pub struct LevelCardMessage(pub String);
+
 /// Component that marks the level card.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Component)]
 struct LevelCard;
@@ -87,13 +94,15 @@ fn trigger_level_transition_state(mut next_state: ResMut<NextState<GameState>>)
 }
 
 fn clean_up_transition_to_resource(mut commands: Commands) {
-    commands.remove_resource::<TransitionTo>()
+    commands.remove_resource::<TransitionTo>();
+    commands.remove_resource::<LevelCardMessage>();
 }
 
 fn spawn_level_card(
     mut commands: Commands,
     mut level_card_events: ResMut<EventScheduler<LevelCardEvent>>,
     transition_to: Res<TransitionTo>,
+    message: Option<Res<LevelCardMessage>>,
     ldtk_assets: Res<Assets<LdtkProject>>,
     assets: Res<AssetServer>,
     asset_holder: Res<AssetHolder>,
@@ -181,6 +190,19 @@ fn spawn_level_card(
                     TextColor(Color::WHITE),
                 ))
                 .insert(FontScale::from(FontSize::Medium));
+            if let Some(message) = message {
+                parent
+                    .spawn((
+                        Text(message.0.clone()),
+                        TextFont::from_font(assets.load("fonts/WayfarersToyBoxRegular-gxxER.ttf")),
+                        TextColor(Color::WHITE),
+                        Node {
+                            margin: UiRect::top(Val::Px(20.)),
+                            ..default()
+                        },
+                    ))
+                    .insert(FontScale::from(FontSize::Small));
+            }
         })
         .insert(LevelCard);
 
@@ -196,10 +218,16 @@ fn load_next_level(
     mut first_card_skipped: Local<bool>,
     transition_to: Res<TransitionTo>,
     asset_holder: Res<AssetHolder>,
+    levels: Query<Entity, With<LevelIid>>,
 ) {
     for event in level_card_events.read() {
         if let LevelCardEvent::Block = event {
-            if *first_card_skipped {
+            if *first_card_skipped && *level_selection == transition_to.0 {
+                // the level is already spawned, so it needs to be respawned to be played again
+                for level_entity in levels.iter() {
+                    commands.entity(level_entity).insert(Respawn);
+                }
+            } else if *first_card_skipped {
                 *level_selection = transition_to.0.clone()
             } else {
                 commands.spawn(LdtkWorldBundle {
@@ -13,6 +13,7 @@ pub mod level_select;
 pub mod level_transition;
 pub mod nine_slice;
 pub mod previous_component;
+pub mod save;
 pub mod sokoban;
 pub mod ui;
 pub mod ui_atlas_image;
@@ -99,6 +100,7 @@ fn main() {
         level_select::LevelSelectPlugin,
         camera::CameraPlugin,
         level_transition::LevelTransitionPlugin,
+        save::SavePlugin,
     ))
     .insert_resource(level_selection.clone())
     .insert_resource(level_transition::TransitionTo(level_selection));
@@ -0,0 +1,122 @@
+//! Plugin providing the persistent save file.
+//!
+//! The save is loaded from `save.json`, next to the asset folder, and written back whenever it
+//! changes.
+//! On the web, the save only lasts until the page is closed.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+/// Plugin providing the persistent save file.
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        let asset_folder = app.get_added_plugins::<AssetPlugin>()[0].file_path.clone();
+
+        // a missing or unreadable save simply starts the game afresh
+        app.insert_resource(load_save(&asset_folder).unwrap_or_default())
+            .insert_resource(SavePath(asset_folder))
+            .add_systems(Update, write_save.run_if(resource_changed::<SaveData>));
+    }
+}
+
+/// Resource storing the player's progress across sessions.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize, Resource)]
+pub struct SaveData {
+    levels: BTreeMap<String, LevelSave>,
+}
+
+/// Progress in a single level, stored in [SaveData].
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+struct LevelSave {
+    collected: BTreeSet<String>,
+}
+
+impl SaveData {
+    /// Records that the collectible with the given iid has been collected.
+    pub fn collect(&mut self, level_iid: &str, collectible_iid: &str) {
+        self.levels
+            .entry(level_iid.to_string())
+            .or_default()
+            .collected
+            .insert(collectible_iid.to_string());
+    }
+
+    /// Returns `true` if the collectible with the given iid has ever been collected.
+    pub fn has_collected(&self, level_iid: &str, collectible_iid: &str) -> bool {
+        self.levels
+            .get(level_iid)
+            .is_some_and(|level| level.collected.contains(collectible_iid))
+    }
+
+    /// Returns how many collectibles have ever been collected in the given level.
+    pub fn collected_count(&self, level_iid: &str) -> usize {
+        self.levels
+            .get(level_iid)
+            .map_or(0, |level| level.collected.len())
+    }
+}
+
+/// Resource storing the asset folder that the save file sits next to.
+#[derive(Clone, Debug, Deref, Resource)]
+struct SavePath(String);
+
+fn load_save(asset_folder: &str) -> std::io::Result<SaveData> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Ok(serde_json::from_reader(BufReader::new(File::open(
+            format!("{asset_folder}/../save.json"),
+        )?))?)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = asset_folder;
+        Ok(SaveData::default())
+    }
+}
+
+fn write_save(save_data: Res<SaveData>, save_path: Res<SavePath>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let result = File::create(format!("{}/../save.json", **save_path)).and_then(|file| {
+            serde_json::to_writer_pretty(BufWriter::new(file), &*save_data)
+                .map_err(std::io::Error::from)
+        });
+
+        if let Err(error) = result {
+            warn!("unable to write save file: {error}");
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = (save_data, save_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_counts_each_collectible_once() {
+        let mut save_data = SaveData::default();
+
+        assert_eq!(save_data.collected_count("level"), 0);
+
+        save_data.collect("level", "candle");
+        save_data.collect("level", "candle");
+        save_data.collect("level", "flower");
+
+        assert_eq!(save_data.collected_count("level"), 2);
+        assert!(save_data.has_collected("level", "candle"));
+        assert!(!save_data.has_collected("other level", "candle"));
+    }
+}
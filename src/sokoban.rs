@@ -4,6 +4,10 @@
 //! Spawn entities with `GridCoords` (from `bevy_ecs_ldtk`) and [SokobanBlock]s to give them
 //! sokoban-style collision.
 //! Then, move entities around with the [SokobanCommands] system parameter.
+//! Every move fires a [MoveEvent], along with a [LeaveEvent] for the tile that was left behind.
+//!
+//! [SokobanBlock::Sticky] entities with [Bonds] will bond to adjacent sticky blocks, and then move
+//! together with them as a group.
 use bevy::{
     ecs::system::SystemParam,
     platform::collections::{HashMap, HashSet},
@@ -25,13 +29,17 @@ pub enum SokobanSets {
     EaseMovement,
     /// Set for the system that updates the logical position of sokoban entities.
     LogicalMovement,
+    /// Set for systems that resolve the consequences of logical movement, like teleportation.
+    ///
+    /// Runs after [SokobanSets::LogicalMovement].
+    MoveResolution,
 }
 
 /// Plugin providing functionality for sokoban-style movement and collision to LDtk levels.
 pub struct SokobanPlugin<S, P, D>
 where
     S: States,
-    P: Push + Component,
+    P: Push<Direction> + Component,
     for<'d> IVec2: Add<&'d D, Output = IVec2>,
     D: Hash + PartialEq + Eq + Clone + Send + Sync + 'static,
 {
@@ -44,7 +52,7 @@ where
 impl<S, P, D> SokobanPlugin<S, P, D>
 where
     S: States,
-    P: Push + Component,
+    P: Push<Direction> + Component,
     for<'d> IVec2: Add<&'d D, Output = IVec2>,
     D: Hash + PartialEq + Eq + Clone + Send + Sync + 'static,
 {
@@ -69,20 +77,30 @@ where
 impl<S, P, D> Plugin for SokobanPlugin<S, P, D>
 where
     S: States,
-    P: Push + Component,
+    P: Push<Direction> + Component,
     for<'d> IVec2: Add<&'d D, Output = IVec2>,
     D: Hash + PartialEq + Eq + Clone + Send + Sync + 'static,
 {
     fn build(&self, app: &mut App) {
         app.add_event::<SokobanCommand<Direction>>()
             .add_event::<PushEvent<Direction>>()
+            .add_event::<MoveEvent<Direction>>()
+            .add_event::<LeaveEvent>()
             .insert_resource(self.layer_identifier.clone())
+            .init_resource::<WrapAround>()
+            .configure_sets(
+                Update,
+                SokobanSets::MoveResolution.after(SokobanSets::LogicalMovement),
+            )
             .add_systems(
                 Update,
-                flush_sokoban_commands::<P, Direction>
-                    .run_if(in_state(self.state.clone()))
-                    .run_if(on_event::<SokobanCommand<Direction>>)
-                    .in_set(SokobanSets::LogicalMovement),
+                (
+                    flush_sokoban_commands::<P, Direction>
+                        .run_if(on_event::<SokobanCommand<Direction>>)
+                        .in_set(SokobanSets::LogicalMovement),
+                    form_sticky_bonds.after(SokobanSets::MoveResolution),
+                )
+                    .run_if(in_state(self.state.clone())),
             )
             // Systems with potential easing end/beginning collisions cannot be in CoreSet::Update
             // see https://github.com/vleue/bevy_easings/issues/23
@@ -99,6 +117,23 @@ where
 #[derive(Debug, Clone, Deref, DerefMut, Resource)]
 struct SokobanLayerIdentifier(String);
 
+/// Resource that makes the sokoban grid wrap around at its edges, like a torus.
+///
+/// When set to `Some(size)`, coordinates leaving the `0..size` range re-enter from the opposite
+/// edge, and collision is resolved against whatever occupies the cell across the seam.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug, Deref, DerefMut, Resource)]
+pub struct WrapAround(pub Option<IVec2>);
+
+impl WrapAround {
+    /// Wraps the given coordinate into the grid, if wrapping is enabled.
+    pub fn wrap(&self, coordinate: IVec2) -> IVec2 {
+        match self.0 {
+            Some(size) => coordinate.rem_euclid(size),
+            None => coordinate,
+        }
+    }
+}
+
 /// Enumerates the four directions that sokoban blocks can be pushed in.
 #[derive(Copy, Clone, Default, Eq, PartialEq, Debug, Hash, Reflect)]
 pub enum Direction {
@@ -169,6 +204,19 @@ impl Direction {
     fn try_add(self, rhs: Direction) -> Result<Direction, OutOfBoundsDirection> {
         Direction::try_from(&(IVec2::ZERO + &self + &rhs))
     }
+
+    /// Returns this direction mirrored horizontally, swapping left and right.
+    pub fn mirrored(self) -> Direction {
+        match self {
+            Direction::UpRight => Direction::UpLeft,
+            Direction::UpLeft => Direction::UpRight,
+            Direction::Left => Direction::Right,
+            Direction::DownLeft => Direction::DownRight,
+            Direction::DownRight => Direction::DownLeft,
+            Direction::Right => Direction::Left,
+            other => other,
+        }
+    }
 }
 
 impl Add<Direction> for Direction {
@@ -199,6 +247,16 @@ where
         /// The direction to move the block in.
         direction: D,
     },
+    /// Move a [SokobanBlock] entity several cells in the given direction at once, leaping over
+    /// anything in between.
+    Jump {
+        /// The [SokobanBlock] entity to move.
+        entity: Entity,
+        /// The direction to jump in.
+        direction: D,
+        /// The number of cells to jump.
+        distance: u32,
+    },
 }
 
 /// System parameter providing an interface for commanding the SokobanPlugin.
@@ -223,6 +281,17 @@ where
         self.writer
             .write(SokobanCommand::Move { entity, direction });
     }
+
+    /// Move a [SokobanBlock] entity `distance` cells in the given direction, without pushing.
+    ///
+    /// The jump only happens if nothing at the landing cell would obstruct the block.
+    pub fn jump_block(&mut self, entity: Entity, direction: D, distance: u32) {
+        self.writer.write(SokobanCommand::Jump {
+            entity,
+            direction,
+            distance,
+        });
+    }
 }
 
 /// Component defining the behavior of sokoban entities on collision.
@@ -233,6 +302,17 @@ pub enum SokobanBlock {
     Static,
     /// The entity can move, push, or be pushed.
     Dynamic,
+    /// The entity can move, push, or be pushed, and bonds to adjacent sticky blocks.
+    ///
+    /// Bonded blocks move together as a group, see [Bonds].
+    Sticky,
+    /// The entity can move or be pushed, but it cannot push other blocks.
+    Weak,
+    /// The entity cannot move, push, or be pushed.
+    ///
+    /// It can be entered by moving in the given direction, but blocks movement in all others.
+    /// Only entry is restricted, entities inside a gate can leave it in any direction.
+    Gate(Direction),
 }
 
 /// Possible outcomes for a block that is pushing another block.
@@ -265,17 +345,28 @@ pub enum PusheeResult {
 /// Abstraction for types that can pushed or be pushed in the context of sokoban.
 ///
 /// Essentially defines the rules of the sokoban game.
-pub trait Push {
-    /// Returns the outcome of this instance pushing another.
-    fn push(&self, pushee: &Self) -> (PusherResult, PusheeResult);
+/// The direction of the push is provided too, so that the rules can be direction-dependent.
+pub trait Push<D> {
+    /// Returns the outcome of this instance pushing another in the given direction.
+    fn push(&self, pushee: &Self, direction: &D) -> (PusherResult, PusheeResult);
 }
 
-impl Push for SokobanBlock {
-    fn push(&self, pushee: &Self) -> (PusherResult, PusheeResult) {
+impl Push<Direction> for SokobanBlock {
+    fn push(&self, pushee: &Self, direction: &Direction) -> (PusherResult, PusheeResult) {
         match (self, pushee) {
-            (_, SokobanBlock::Static) => (PusherResult::Blocked, PusheeResult::NotPushed),
-            (SokobanBlock::Static, _) => (PusherResult::Blocked, PusheeResult::Pushed),
-            (SokobanBlock::Dynamic, _) => (PusherResult::NotBlocked, PusheeResult::Pushed),
+            (_, SokobanBlock::Gate(gate_direction)) if gate_direction == direction => {
+                (PusherResult::NotBlocked, PusheeResult::NotPushed)
+            }
+            (_, SokobanBlock::Static | SokobanBlock::Gate(_)) => {
+                (PusherResult::Blocked, PusheeResult::NotPushed)
+            }
+            (SokobanBlock::Static | SokobanBlock::Gate(_), _) => {
+                (PusherResult::Blocked, PusheeResult::Pushed)
+            }
+            (SokobanBlock::Weak, _) => (PusherResult::Blocked, PusheeResult::NotPushed),
+            (SokobanBlock::Dynamic | SokobanBlock::Sticky, _) => {
+                (PusherResult::NotBlocked, PusheeResult::Pushed)
+            }
         }
     }
 }
@@ -316,6 +407,94 @@ impl SokobanBlock {
     pub fn new_dynamic(_: &EntityInstance) -> SokobanBlock {
         SokobanBlock::Dynamic
     }
+
+    /// Constructor returning [SokobanBlock::Weak].
+    ///
+    /// Compatible with the `with` attribute for `#[derive(LdtkEntity)]`:
+    /// ```
+    /// use bevy_ecs_ldtk::*;
+    ///
+    /// #[derive(Bundle, LdtkEntity)]
+    /// struct MyLdtkEntity {
+    ///     #[grid_coords]
+    ///     grid_coords: GridCoords,
+    ///     #[with(SokobanBlock::new_weak)]
+    ///     sokoban_block: SokobanBlock,
+    /// }
+    /// ```
+    pub fn new_weak(_: &EntityInstance) -> SokobanBlock {
+        SokobanBlock::Weak
+    }
+
+    /// Constructor returning [SokobanBlock::Sticky].
+    ///
+    /// Compatible with the `with` attribute for `#[derive(LdtkEntity)]`:
+    /// ```
+    /// use bevy_ecs_ldtk::*;
+    ///
+    /// #[derive(Bundle, LdtkEntity)]
+    /// struct MyLdtkEntity {
+    ///     #[grid_coords]
+    ///     grid_coords: GridCoords,
+    ///     #[with(SokobanBlock::new_sticky)]
+    ///     sokoban_block: SokobanBlock,
+    ///     bonds: Bonds,
+    /// }
+    /// ```
+    pub fn new_sticky(_: &EntityInstance) -> SokobanBlock {
+        SokobanBlock::Sticky
+    }
+}
+
+/// Component storing the other sokoban entities that a [SokobanBlock::Sticky] is bonded to.
+///
+/// Bonds form automatically when two sticky blocks become orthogonally adjacent.
+/// Whenever one member of a bonded group moves, the rest of the group moves with it.
+/// The bonds are never broken by the plugin itself, but they can be replaced, for example by
+/// rewinding history.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Component, Deref, DerefMut)]
+pub struct Bonds(HashSet<Entity>);
+
+/// Component defining which collision layers a [SokobanBlock] belongs to and collides with.
+///
+/// Both fields are bitmasks, one bit per layer.
+/// Two blocks only interact if each one's `filters` overlap the other's `memberships`.
+/// Otherwise, they can occupy the same tile and simply pass through each other.
+///
+/// Blocks without this component belong to, and collide with, every layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Component)]
+pub struct CollisionLayers {
+    /// The layers this block belongs to.
+    pub memberships: u32,
+    /// The layers this block collides with.
+    pub filters: u32,
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        CollisionLayers::ALL
+    }
+}
+
+impl CollisionLayers {
+    /// Collision layers that belong to, and collide with, every layer.
+    pub const ALL: CollisionLayers = CollisionLayers {
+        memberships: u32::MAX,
+        filters: u32::MAX,
+    };
+
+    /// Construct a new [CollisionLayers] from membership and filter bitmasks.
+    pub fn new(memberships: u32, filters: u32) -> Self {
+        CollisionLayers {
+            memberships,
+            filters,
+        }
+    }
+
+    /// Returns true if blocks with these layers and the `other` layers collide.
+    pub fn interacts_with(&self, other: &CollisionLayers) -> bool {
+        (self.filters & other.memberships) != 0 && (other.filters & self.memberships) != 0
+    }
 }
 
 /// Component that marks [SokobanBlock]s that should fire [PushEvent]s when they push other blocks.
@@ -335,28 +514,80 @@ where
     pub direction: D,
 }
 
+/// Event that fires for every [SokobanBlock] that is moved by a [SokobanCommand].
+///
+/// This includes blocks that were moved by being pushed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Event)]
+pub struct MoveEvent<D>
+where
+    for<'d> IVec2: Add<&'d D, Output = IVec2>,
+    D: Hash + PartialEq + Eq + Clone + Send + Sync + 'static,
+{
+    /// The [SokobanBlock] entity that moved.
+    pub entity: Entity,
+    /// The direction of the move.
+    pub direction: D,
+    /// The tile the block moved to.
+    pub grid_coords: GridCoords,
+    /// The [PushTracker] entity that pushed the block, if it was pushed by one.
+    pub pusher: Option<Entity>,
+}
+
+/// Event that fires for every [SokobanBlock] that leaves a tile because of a [SokobanCommand].
+///
+/// A block moving several tiles at once, like with a jump, only leaves the tile it started on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Event)]
+pub struct LeaveEvent {
+    /// The [SokobanBlock] entity that left the tile.
+    pub entity: Entity,
+    /// The tile that was left.
+    pub grid_coords: GridCoords,
+}
+
+/// Component that marks sokoban entities whose next change in `GridCoords` should be shown as an
+/// instant warp rather than a slide.
+///
+/// The component is removed again once the warp has been shown.
+#[derive(Copy, Clone, Default, Debug, Component)]
+pub struct Warp;
+
 fn ease_movement(
     mut commands: Commands,
     mut grid_coords_query: Query<
-        (Entity, &GridCoords, &Transform),
+        (Entity, &GridCoords, &Transform, Has<Warp>),
         (Changed<GridCoords>, With<SokobanBlock>),
     >,
     layers: Query<&LayerMetadata>,
     layer_id: Res<SokobanLayerIdentifier>,
 ) {
-    for (entity, &grid_coords, transform) in grid_coords_query.iter_mut() {
+    for (entity, &grid_coords, transform, warp) in grid_coords_query.iter_mut() {
         if let Some(LayerMetadata { grid_size, .. }) =
             layers.iter().find(|l| l.identifier == **layer_id)
         {
             let xy = grid_coords_to_translation(grid_coords, IVec2::splat(*grid_size));
-
-            commands.entity(entity).insert(transform.ease_to(
-                Transform::from_xyz(xy.x, xy.y, transform.translation.z),
-                EaseFunction::CubicOut,
-                EasingType::Once {
-                    duration: std::time::Duration::from_millis(110),
-                },
-            ));
+            let destination = Transform::from_xyz(xy.x, xy.y, transform.translation.z)
+                .with_rotation(transform.rotation);
+
+            if warp {
+                // appear at the destination, growing from nothing
+                commands.entity(entity).remove::<Warp>().insert(
+                    destination.with_scale(Vec3::ZERO).ease_to(
+                        destination,
+                        EaseFunction::BackOut,
+                        EasingType::Once {
+                            duration: std::time::Duration::from_millis(220),
+                        },
+                    ),
+                );
+            } else {
+                commands.entity(entity).insert(transform.ease_to(
+                    destination,
+                    EaseFunction::CubicOut,
+                    EasingType::Once {
+                        duration: std::time::Duration::from_millis(110),
+                    },
+                ));
+            }
         }
     }
 }
@@ -364,27 +595,33 @@ fn ease_movement(
 #[derive(Clone, Debug)]
 struct EntityCollisionGeographicMap<'a, P>
 where
-    P: Push + Component,
+    P: Component,
 {
     coordinate_table: HashMap<IVec2, HashSet<Entity>>,
     entity_table: HashMap<Entity, (IVec2, &'a P)>,
+    bond_table: HashMap<Entity, &'a Bonds>,
+    layer_table: HashMap<Entity, &'a CollisionLayers>,
+    wrap_around: WrapAround,
 }
 
 impl<'a, P> Default for EntityCollisionGeographicMap<'a, P>
 where
-    P: Push + Component,
+    P: Component,
 {
     fn default() -> Self {
         EntityCollisionGeographicMap {
             coordinate_table: HashMap::new(),
             entity_table: HashMap::new(),
+            bond_table: HashMap::new(),
+            layer_table: HashMap::new(),
+            wrap_around: WrapAround::default(),
         }
     }
 }
 
 impl<'a, P> FromIterator<(Entity, IVec2, &'a P)> for EntityCollisionGeographicMap<'a, P>
 where
-    P: Push + Component,
+    P: Component,
 {
     fn from_iter<T: IntoIterator<Item = (Entity, IVec2, &'a P)>>(iter: T) -> Self {
         iter.into_iter().fold(
@@ -392,6 +629,9 @@ where
             |EntityCollisionGeographicMap {
                  mut coordinate_table,
                  mut entity_table,
+                 bond_table,
+                 layer_table,
+                 wrap_around,
              },
              (entity, coordinate, push_block)| {
                 coordinate_table
@@ -403,6 +643,9 @@ where
                 EntityCollisionGeographicMap {
                     coordinate_table,
                     entity_table,
+                    bond_table,
+                    layer_table,
+                    wrap_around,
                 }
             },
         )
@@ -411,8 +654,26 @@ where
 
 impl<'a, P> EntityCollisionGeographicMap<'a, P>
 where
-    P: Push + Component,
+    P: Component,
 {
+    fn with_bonds(mut self, bonds: impl IntoIterator<Item = (Entity, &'a Bonds)>) -> Self {
+        self.bond_table.extend(bonds);
+        self
+    }
+
+    fn with_layers(
+        mut self,
+        layers: impl IntoIterator<Item = (Entity, &'a CollisionLayers)>,
+    ) -> Self {
+        self.layer_table.extend(layers);
+        self
+    }
+
+    fn with_wrap_around(mut self, wrap_around: WrapAround) -> Self {
+        self.wrap_around = wrap_around;
+        self
+    }
+
     fn get_coordinate_and_block(&self, entity: &Entity) -> Option<&(IVec2, &'a P)> {
         self.entity_table.get(entity)
     }
@@ -426,6 +687,20 @@ where
         self.coordinate_table.get(coordinate)
     }
 
+    fn get_bonds(&self, entity: &Entity) -> impl Iterator<Item = &Entity> {
+        self.bond_table
+            .get(entity)
+            .into_iter()
+            .flat_map(|bonds| bonds.iter())
+    }
+
+    fn get_layers(&self, entity: &Entity) -> &CollisionLayers {
+        self.layer_table
+            .get(entity)
+            .copied()
+            .unwrap_or(&CollisionLayers::ALL)
+    }
+
     /// returns a list of entities that would be pushed
     fn simulate_move_entity<D>(
         &self,
@@ -433,6 +708,67 @@ where
         direction: &D,
     ) -> (PusherResult, HashSet<Entity>, HashSet<PushEvent<D>>)
     where
+        P: Push<D>,
+        for<'d> IVec2: Add<&'d D, Output = IVec2>,
+        D: Hash + PartialEq + Eq + Clone + Send + Sync + 'static,
+    {
+        self.simulate_move_group(pusher_entity, direction, &mut HashSet::new())
+    }
+
+    /// Returns whether the given entity can jump `distance` cells in the given direction.
+    ///
+    /// Jumps ignore everything between the start and the landing cell, and never push.
+    /// Anything at the landing cell that would block or be pushed by the jumper obstructs it.
+    fn simulate_jump_entity<D>(&self, jumper_entity: &Entity, direction: &D, distance: u32) -> bool
+    where
+        P: Push<D>,
+        for<'d> IVec2: Add<&'d D, Output = IVec2>,
+        D: Hash + PartialEq + Eq + Clone + Send + Sync + 'static,
+    {
+        let Some((jumper_coordinate, jumper_block)) = self.get_coordinate_and_block(jumper_entity)
+        else {
+            return false;
+        };
+
+        let landing = self
+            .wrap_around
+            .wrap((0..distance).fold(*jumper_coordinate, |coordinate, _| coordinate + direction));
+        if &landing == jumper_coordinate {
+            return false;
+        }
+
+        self.get_entities_at_coords(&landing)
+            .into_iter()
+            .flatten()
+            .filter(|obstacle_entity| {
+                self.get_layers(jumper_entity)
+                    .interacts_with(self.get_layers(obstacle_entity))
+            })
+            .all(|obstacle_entity| {
+                let obstacle_block = self
+                    .get_block(obstacle_entity)
+                    .expect("entities in coordinate table should also exist in entity table");
+
+                matches!(
+                    jumper_block.push(obstacle_block, direction),
+                    (PusherResult::NotBlocked, PusheeResult::NotPushed)
+                )
+            })
+    }
+
+    /// Simulates the move of the given entity and every block bonded to it.
+    ///
+    /// `moving` accumulates all entities that have joined the move so far.
+    /// These entities vacate their cells, so they never obstruct each other.
+    /// If any part of the move is blocked, nothing moves at all.
+    fn simulate_move_group<D>(
+        &self,
+        pusher_entity: &Entity,
+        direction: &D,
+        moving: &mut HashSet<Entity>,
+    ) -> (PusherResult, HashSet<Entity>, HashSet<PushEvent<D>>)
+    where
+        P: Push<D>,
         for<'d> IVec2: Add<&'d D, Output = IVec2>,
         D: Hash + PartialEq + Eq + Clone + Send + Sync + 'static,
     {
@@ -441,88 +777,146 @@ where
             return default();
         };
 
-        let destination = *pusher_coordinate + direction;
+        let destination = self.wrap_around.wrap(*pusher_coordinate + direction);
         if &destination == pusher_coordinate {
             return default();
         }
 
-        let (pusher_result, mut moved_entities, mut push_events) = self
+        moving.insert(*pusher_entity);
+
+        let mut pusher_result = PusherResult::NotBlocked;
+        let mut moved_entities = HashSet::new();
+        let mut pushed_entities = HashSet::new();
+        let mut push_events = HashSet::new();
+
+        for bonded_entity in self.get_bonds(pusher_entity) {
+            if moving.contains(bonded_entity) {
+                continue;
+            }
+
+            let (bonded_result, bonded_moved_entities, bonded_push_events) =
+                self.simulate_move_group(bonded_entity, direction, moving);
+
+            pusher_result = pusher_result.reduce(&bonded_result);
+            moved_entities.extend(bonded_moved_entities);
+            push_events.extend(bonded_push_events);
+        }
+
+        for pushee_entity in self
             .get_entities_at_coords(&destination)
-            .iter()
-            .copied()
+            .into_iter()
             .flatten()
-            .map(|pushee_entity| {
-                let pushee_block = self
-                    .get_block(pushee_entity)
-                    .expect("entities in coordinate table should also exist in entity table");
+        {
+            if moving.contains(pushee_entity)
+                || !self
+                    .get_layers(pusher_entity)
+                    .interacts_with(self.get_layers(pushee_entity))
+            {
+                continue;
+            }
 
-                let (our_pusher_result, pushee_result) = pusher_block.push(pushee_block);
+            let pushee_block = self
+                .get_block(pushee_entity)
+                .expect("entities in coordinate table should also exist in entity table");
 
-                let (their_pusher_result, moved_entities, push_events) = match pushee_result {
-                    PusheeResult::Pushed => self.simulate_move_entity(pushee_entity, direction),
-                    PusheeResult::NotPushed => default(),
-                };
+            let (our_pusher_result, pushee_result) = pusher_block.push(pushee_block, direction);
+            pusher_result = pusher_result.reduce(&our_pusher_result);
 
-                let pusher_result = our_pusher_result.reduce(&their_pusher_result);
+            if let PusheeResult::Pushed = pushee_result {
+                let (their_pusher_result, their_moved_entities, their_push_events) =
+                    self.simulate_move_group(pushee_entity, direction, moving);
 
-                (pusher_result, moved_entities, push_events)
-            })
-            .reduce(
-                |(
-                    previous_pusher_result,
-                    mut previous_moved_entities,
-                    mut previous_push_events,
-                ),
-                 (pusher_result, moved_entities, push_events)| {
-                    previous_moved_entities.extend(moved_entities);
-                    previous_push_events.extend(push_events);
-
-                    (
-                        previous_pusher_result.reduce(&pusher_result),
-                        previous_moved_entities,
-                        previous_push_events,
-                    )
-                },
-            )
-            .unwrap_or_default();
+                pusher_result = pusher_result.reduce(&their_pusher_result);
+                pushed_entities.extend(their_moved_entities);
+                push_events.extend(their_push_events);
+            }
+        }
 
-        if !moved_entities.is_empty() {
+        if pusher_result == PusherResult::Blocked {
+            return (PusherResult::Blocked, HashSet::new(), HashSet::new());
+        }
+
+        if !pushed_entities.is_empty() {
             push_events.insert(PushEvent {
                 pusher: *pusher_entity,
                 direction: direction.clone(),
             });
         }
 
-        if pusher_result == PusherResult::NotBlocked {
-            moved_entities.insert(*pusher_entity);
-        }
+        moved_entities.extend(pushed_entities);
+        moved_entities.insert(*pusher_entity);
 
         (pusher_result, moved_entities, push_events)
     }
 }
 
 fn flush_sokoban_commands<P, D>(
-    mut grid_coords_query: Query<(Entity, &mut GridCoords, &P, Has<PushTracker>)>,
+    mut commands: Commands,
+    mut grid_coords_query: Query<(
+        Entity,
+        &mut GridCoords,
+        &P,
+        Option<&Bonds>,
+        Option<&CollisionLayers>,
+        Has<PushTracker>,
+    )>,
     mut sokoban_commands: EventReader<SokobanCommand<D>>,
     mut push_events: EventWriter<PushEvent<D>>,
+    mut move_events: EventWriter<MoveEvent<D>>,
+    mut leave_events: EventWriter<LeaveEvent>,
+    wrap_around: Res<WrapAround>,
 ) where
-    P: Push + Component,
+    P: Push<D> + Component,
     for<'d> IVec2: Add<&'d D, Output = IVec2>,
     D: Hash + PartialEq + Eq + Clone + Send + Sync + 'static,
 {
     for sokoban_command in sokoban_commands.read() {
-        let SokobanCommand::Move { entity, direction } = sokoban_command;
-
-        let (_, entities_to_move, push_events_to_send) = {
+        let (entities_to_move, push_events_to_send, direction, distance, pusher) = {
             // regenerate map per command to get map updates from previous command
             let entity_collision_geographic_map = grid_coords_query
                 .iter()
-                .map(|(entity, grid_coords, sokoban_block, _)| {
+                .map(|(entity, grid_coords, sokoban_block, ..)| {
                     (entity, IVec2::from(*grid_coords), sokoban_block)
                 })
-                .collect::<EntityCollisionGeographicMap<P>>();
-
-            entity_collision_geographic_map.simulate_move_entity(entity, direction)
+                .collect::<EntityCollisionGeographicMap<P>>()
+                .with_bonds(
+                    grid_coords_query
+                        .iter()
+                        .filter_map(|(entity, _, _, bonds, ..)| bonds.map(|bonds| (entity, bonds))),
+                )
+                .with_layers(grid_coords_query.iter().filter_map(
+                    |(entity, _, _, _, layers, _)| layers.map(|layers| (entity, layers)),
+                ));
+
+            let entity_collision_geographic_map =
+                entity_collision_geographic_map.with_wrap_around(*wrap_around);
+
+            match sokoban_command {
+                SokobanCommand::Move { entity, direction } => {
+                    let (_, entities_to_move, push_events_to_send) =
+                        entity_collision_geographic_map.simulate_move_entity(entity, direction);
+
+                    let pusher = grid_coords_query
+                        .get(*entity)
+                        .is_ok_and(|(.., is_push_tracker)| is_push_tracker)
+                        .then_some(*entity);
+
+                    (entities_to_move, push_events_to_send, direction, 1, pusher)
+                }
+                SokobanCommand::Jump {
+                    entity,
+                    direction,
+                    distance,
+                } => {
+                    let entities_to_move = entity_collision_geographic_map
+                        .simulate_jump_entity(entity, direction, *distance)
+                        .then_some(*entity)
+                        .into_iter()
+                        .collect();
+
+                    (entities_to_move, HashSet::new(), direction, *distance, None)
+                }
+            }
         };
 
         entities_to_move.iter().for_each(|entity_to_move| {
@@ -530,8 +924,29 @@ fn flush_sokoban_commands<P, D>(
                 .get_mut(*entity_to_move)
                 .expect("pushed entity should be valid sokoban entity");
 
-            let new_coords = IVec2::from(*grid_coords) + direction;
+            let unwrapped_coords = (0..distance)
+                .fold(IVec2::from(*grid_coords), |coordinate, _| {
+                    coordinate + direction
+                });
+            let new_coords = wrap_around.wrap(unwrapped_coords);
+
+            leave_events.write(LeaveEvent {
+                entity: *entity_to_move,
+                grid_coords: *grid_coords,
+            });
+
             *grid_coords = GridCoords::from(new_coords);
+
+            if new_coords != unwrapped_coords {
+                commands.entity(*entity_to_move).insert(Warp);
+            }
+
+            move_events.write(MoveEvent {
+                entity: *entity_to_move,
+                direction: direction.clone(),
+                grid_coords: *grid_coords,
+                pusher: pusher.filter(|pusher| pusher != entity_to_move),
+            });
         });
 
         push_events_to_send
@@ -549,6 +964,35 @@ fn flush_sokoban_commands<P, D>(
     }
 }
 
+fn form_sticky_bonds(
+    moved_blocks: Query<(), (With<Bonds>, Changed<GridCoords>)>,
+    mut sticky_blocks: Query<(Entity, &GridCoords, &SokobanBlock, &mut Bonds)>,
+) {
+    if moved_blocks.is_empty() {
+        return;
+    }
+
+    let sticky_coordinates: HashMap<IVec2, Entity> = sticky_blocks
+        .iter()
+        .filter(|(_, _, block, _)| **block == SokobanBlock::Sticky)
+        .map(|(entity, grid_coords, ..)| (IVec2::from(*grid_coords), entity))
+        .collect();
+
+    for (entity, grid_coords, block, mut bonds) in sticky_blocks.iter_mut() {
+        if *block != SokobanBlock::Sticky {
+            continue;
+        }
+
+        for offset in [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y] {
+            if let Some(neighbor) = sticky_coordinates.get(&(IVec2::from(*grid_coords) + offset)) {
+                if *neighbor != entity && !bonds.contains(neighbor) {
+                    bonds.insert(*neighbor);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -631,6 +1075,261 @@ mod tests {
         );
     }
 
+    #[test]
+    fn enter_gate_only_in_its_direction() {
+        let pusher = Entity::from_raw(0);
+        let gate = Entity::from_raw(1);
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(0, 1), &SokobanBlock::Dynamic),
+            (
+                gate,
+                IVec2::new(1, 1),
+                &SokobanBlock::Gate(Direction::Right),
+            ),
+        ]);
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Right),
+            (
+                PusherResult::NotBlocked,
+                HashSet::from_iter([pusher]),
+                HashSet::new()
+            )
+        );
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(2, 1), &SokobanBlock::Dynamic),
+            (
+                gate,
+                IVec2::new(1, 1),
+                &SokobanBlock::Gate(Direction::Right),
+            ),
+        ]);
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Left),
+            (PusherResult::Blocked, HashSet::new(), HashSet::new())
+        );
+    }
+
+    #[test]
+    fn move_through_non_colliding_layers() {
+        let pusher = Entity::from_raw(0);
+        let fence = Entity::from_raw(1);
+        let gravestone = Entity::from_raw(2);
+
+        let ghost_layers = CollisionLayers::new(0b001, 0b100);
+        let fence_layers = CollisionLayers::new(0b010, u32::MAX);
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(0, 1), &SokobanBlock::Dynamic),
+            (fence, IVec2::new(1, 1), &SokobanBlock::Static),
+            (gravestone, IVec2::new(0, 2), &SokobanBlock::Dynamic),
+        ])
+        .with_layers([(pusher, &ghost_layers), (fence, &fence_layers)]);
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Right),
+            (
+                PusherResult::NotBlocked,
+                HashSet::from_iter([pusher]),
+                HashSet::new()
+            )
+        );
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Up),
+            (
+                PusherResult::NotBlocked,
+                HashSet::from_iter([pusher, gravestone]),
+                HashSet::from_iter([PushEvent {
+                    pusher,
+                    direction: Direction::Up
+                }])
+            )
+        );
+    }
+
+    #[test]
+    fn weak_block_cannot_push() {
+        let pusher = Entity::from_raw(0);
+        let pushee = Entity::from_raw(1);
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(0, 1), &SokobanBlock::Weak),
+            (pushee, IVec2::new(1, 1), &SokobanBlock::Dynamic),
+        ]);
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Right),
+            (PusherResult::Blocked, HashSet::new(), HashSet::new())
+        );
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pushee, &Direction::Left),
+            (
+                PusherResult::NotBlocked,
+                HashSet::from_iter([pusher, pushee]),
+                HashSet::from_iter([PushEvent {
+                    pusher: pushee,
+                    direction: Direction::Left
+                }])
+            )
+        );
+    }
+
+    #[test]
+    fn push_across_wrap_around_seam() {
+        let pusher = Entity::from_raw(0);
+        let pushee = Entity::from_raw(1);
+        let wall = Entity::from_raw(2);
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(2, 1), &SokobanBlock::Dynamic),
+            (pushee, IVec2::new(0, 1), &SokobanBlock::Dynamic),
+        ])
+        .with_wrap_around(WrapAround(Some(IVec2::new(3, 3))));
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Right),
+            (
+                PusherResult::NotBlocked,
+                HashSet::from_iter([pusher, pushee]),
+                HashSet::from_iter([PushEvent {
+                    pusher,
+                    direction: Direction::Right
+                }])
+            )
+        );
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(1, 0), &SokobanBlock::Dynamic),
+            (wall, IVec2::new(1, 2), &SokobanBlock::Static),
+        ])
+        .with_wrap_around(WrapAround(Some(IVec2::new(3, 3))));
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Down),
+            (PusherResult::Blocked, HashSet::new(), HashSet::new())
+        );
+    }
+
+    #[test]
+    fn jump_over_obstacles_without_pushing() {
+        let jumper = Entity::from_raw(0);
+        let obstacle = Entity::from_raw(1);
+        let landing_block = Entity::from_raw(2);
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (jumper, IVec2::new(0, 1), &SokobanBlock::Dynamic),
+            (obstacle, IVec2::new(1, 1), &SokobanBlock::Static),
+            (landing_block, IVec2::new(3, 1), &SokobanBlock::Dynamic),
+        ]);
+
+        assert!(collision_map.simulate_jump_entity(&jumper, &Direction::Right, 2));
+        assert!(!collision_map.simulate_jump_entity(&jumper, &Direction::Right, 1));
+        assert!(!collision_map.simulate_jump_entity(&jumper, &Direction::Right, 3));
+    }
+
+    #[test]
+    fn push_sticky_group_into_empty() {
+        let pusher = Entity::from_raw(0);
+        let sticky_a = Entity::from_raw(1);
+        let sticky_b = Entity::from_raw(2);
+
+        let bonds_a = Bonds(HashSet::from_iter([sticky_b]));
+        let bonds_b = Bonds(HashSet::from_iter([sticky_a]));
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(0, 1), &SokobanBlock::Dynamic),
+            (sticky_a, IVec2::new(1, 1), &SokobanBlock::Sticky),
+            (sticky_b, IVec2::new(1, 2), &SokobanBlock::Sticky),
+        ])
+        .with_bonds([(sticky_a, &bonds_a), (sticky_b, &bonds_b)]);
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Right),
+            (
+                PusherResult::NotBlocked,
+                HashSet::from_iter([pusher, sticky_a, sticky_b]),
+                HashSet::from_iter([PushEvent {
+                    pusher,
+                    direction: Direction::Right
+                }])
+            )
+        );
+    }
+
+    #[test]
+    fn push_sticky_group_with_blocked_member() {
+        let pusher = Entity::from_raw(0);
+        let sticky_a = Entity::from_raw(1);
+        let sticky_b = Entity::from_raw(2);
+        let wall = Entity::from_raw(3);
+
+        let bonds_a = Bonds(HashSet::from_iter([sticky_b]));
+        let bonds_b = Bonds(HashSet::from_iter([sticky_a]));
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(0, 1), &SokobanBlock::Dynamic),
+            (sticky_a, IVec2::new(1, 1), &SokobanBlock::Sticky),
+            (sticky_b, IVec2::new(1, 2), &SokobanBlock::Sticky),
+            (wall, IVec2::new(2, 2), &SokobanBlock::Static),
+        ])
+        .with_bonds([(sticky_a, &bonds_a), (sticky_b, &bonds_b)]);
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Right),
+            (PusherResult::Blocked, HashSet::new(), HashSet::new())
+        );
+    }
+
+    #[test]
+    fn push_sticky_group_back_into_pusher() {
+        let pusher = Entity::from_raw(0);
+        let sticky_a = Entity::from_raw(1);
+        let sticky_b = Entity::from_raw(2);
+        let sticky_c = Entity::from_raw(3);
+        let sticky_d = Entity::from_raw(4);
+        let sticky_e = Entity::from_raw(5);
+
+        // the group wraps around the pusher, so its last member moves into the pusher's cell
+        let bonds_a = Bonds(HashSet::from_iter([sticky_b]));
+        let bonds_b = Bonds(HashSet::from_iter([sticky_a, sticky_c]));
+        let bonds_c = Bonds(HashSet::from_iter([sticky_b, sticky_d]));
+        let bonds_d = Bonds(HashSet::from_iter([sticky_c, sticky_e]));
+        let bonds_e = Bonds(HashSet::from_iter([sticky_d]));
+
+        let collision_map = EntityCollisionGeographicMap::from_iter([
+            (pusher, IVec2::new(1, 1), &SokobanBlock::Dynamic),
+            (sticky_a, IVec2::new(2, 1), &SokobanBlock::Sticky),
+            (sticky_b, IVec2::new(2, 0), &SokobanBlock::Sticky),
+            (sticky_c, IVec2::new(1, 0), &SokobanBlock::Sticky),
+            (sticky_d, IVec2::new(0, 0), &SokobanBlock::Sticky),
+            (sticky_e, IVec2::new(0, 1), &SokobanBlock::Sticky),
+        ])
+        .with_bonds([
+            (sticky_a, &bonds_a),
+            (sticky_b, &bonds_b),
+            (sticky_c, &bonds_c),
+            (sticky_d, &bonds_d),
+            (sticky_e, &bonds_e),
+        ]);
+
+        assert_eq!(
+            collision_map.simulate_move_entity(&pusher, &Direction::Right),
+            (
+                PusherResult::NotBlocked,
+                HashSet::from_iter([pusher, sticky_a, sticky_b, sticky_c, sticky_d, sticky_e]),
+                HashSet::from_iter([PushEvent {
+                    pusher,
+                    direction: Direction::Right
+                }])
+            )
+        );
+    }
+
     fn app_setup() -> App {
         #[derive(Clone, PartialEq, Eq, Debug, Default, Hash, States)]
         enum State {
@@ -700,6 +1399,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn adjacent_sticky_blocks_bond_and_move_together() {
+        let mut app = app_setup();
+
+        let sticky_a = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(0, 0),
+                SokobanBlock::Sticky,
+                Bonds::default(),
+            ))
+            .id();
+        let sticky_b = app
+            .world_mut()
+            .spawn((
+                GridCoords::new(2, 0),
+                SokobanBlock::Sticky,
+                Bonds::default(),
+            ))
+            .id();
+
+        app.update();
+
+        assert!(app.world().get::<Bonds>(sticky_a).unwrap().is_empty());
+
+        let mut system_state: SystemState<SokobanCommands<Direction>> =
+            SystemState::new(app.world_mut());
+        let mut sokoban_commands = system_state.get_mut(app.world_mut());
+
+        sokoban_commands.move_block(sticky_a, super::Direction::Right);
+
+        system_state.apply(app.world_mut());
+
+        app.update();
+
+        assert!(app
+            .world()
+            .get::<Bonds>(sticky_a)
+            .unwrap()
+            .contains(&sticky_b));
+        assert!(app
+            .world()
+            .get::<Bonds>(sticky_b)
+            .unwrap()
+            .contains(&sticky_a));
+
+        let mut sokoban_commands = system_state.get_mut(app.world_mut());
+
+        sokoban_commands.move_block(sticky_b, super::Direction::Up);
+
+        system_state.apply(app.world_mut());
+
+        app.update();
+
+        assert_eq!(
+            *app.world().entity(sticky_a).get::<GridCoords>().unwrap(),
+            GridCoords::new(1, 1)
+        );
+        assert_eq!(
+            *app.world().entity(sticky_b).get::<GridCoords>().unwrap(),
+            GridCoords::new(2, 1)
+        );
+    }
+
     #[test]
     fn push_tracker_sends_events() {
         let mut app = app_setup();
@@ -738,4 +1501,49 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn moved_blocks_send_leave_events() {
+        let mut app = app_setup();
+
+        let block_a = app
+            .world_mut()
+            .spawn((GridCoords::new(1, 1), SokobanBlock::Dynamic))
+            .id();
+        let block_b = app
+            .world_mut()
+            .spawn((GridCoords::new(1, 2), SokobanBlock::Dynamic))
+            .id();
+        app.world_mut()
+            .spawn((GridCoords::new(0, 3), SokobanBlock::Static));
+
+        let mut system_state: SystemState<SokobanCommands<Direction>> =
+            SystemState::new(app.world_mut());
+        let mut sokoban_commands = system_state.get_mut(app.world_mut());
+
+        // the second move is blocked, so nothing leaves its tile
+        sokoban_commands.move_block(block_a, super::Direction::Up);
+        sokoban_commands.move_block(block_b, super::Direction::Left);
+
+        system_state.apply(app.world_mut());
+
+        app.update();
+
+        let events = app.world().resource::<Events<LeaveEvent>>();
+        let mut reader = events.get_cursor();
+
+        assert_eq!(
+            reader.read(events).copied().collect::<HashSet<_>>(),
+            HashSet::from_iter([
+                LeaveEvent {
+                    entity: block_a,
+                    grid_coords: GridCoords::new(1, 1),
+                },
+                LeaveEvent {
+                    entity: block_b,
+                    grid_coords: GridCoords::new(1, 2),
+                },
+            ])
+        );
+    }
 }